@@ -0,0 +1,346 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+// installed addons, and the AddOns.txt each character keeps of which ones it has turned on:
+//
+//   WeakAuras: enabled
+//   Details: disabled
+//
+// addons live in {install}/{version}/Interface/AddOns/{addon}, described by a .toc file:
+//
+//   ## Interface: 110005
+//   ## Title: WeakAuras
+//   ## Version: 5.17.2
+//
+// an addon can have a .toc per flavor (WeakAuras_Mainline.toc, WeakAuras_Vanilla.toc), each with
+// the interface versions it supports. the first part of an interface version is the expansion.
+
+use std::{ffi::OsStr, fs, io::{Error, ErrorKind}, path::{Path, PathBuf}};
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Toc {
+    pub title: Option<String>,
+    pub version: Option<String>,
+    // every interface version the addon says it supports
+    pub interface: Vec<u32>,
+}
+
+// which client a version folder belongs to, as far as addons are concerned
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Flavor {
+    Mainline,
+    // progression classic, which moves through the expansions after vanilla
+    Classic,
+    Vanilla,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AddonStatus {
+    Installed,
+    Missing,
+    // installed in the destination, but an older version than the source's
+    Outdated { src: String, dst: String },
+}
+
+// one addon from a character's AddOns.txt, checked against a version's installed addons
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AddonCheck {
+    pub name: String,
+    pub enabled: bool,
+    pub status: AddonStatus,
+}
+
+// an addon folder in the source version, and how it would fare in the destination
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AddonFolder {
+    pub name: String,
+    // every interface version in any of its .toc files
+    pub interface: Vec<u32>,
+    // whether any of those is for the destination's flavor
+    pub supported: bool,
+    pub status: AddonStatus,
+}
+
+impl std::fmt::Display for Flavor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Flavor::Mainline => "Retail",
+            Flavor::Classic => "Classic",
+            Flavor::Vanilla => "Classic Era",
+        })
+    }
+}
+
+impl Flavor {
+    // from the version folder's name, e.g. _classic_era_ptr_
+    pub fn of(version: &OsStr) -> Flavor {
+        let name = version.to_string_lossy();
+        if name.starts_with("_classic_era") {
+            Flavor::Vanilla
+        } else if name.starts_with("_classic") {
+            Flavor::Classic
+        } else {
+            Flavor::Mainline
+        }
+    }
+
+    pub fn supports(&self, interface: u32) -> bool {
+        match self {
+            Flavor::Mainline => interface >= 100000,
+            Flavor::Classic => (20000..100000).contains(&interface),
+            Flavor::Vanilla => interface < 20000,
+        }
+    }
+}
+
+impl std::fmt::Display for AddonCheck {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.status {
+            AddonStatus::Installed => write!(f, "{} is installed", self.name),
+            AddonStatus::Missing => write!(f, "{} isn't installed in the target version", self.name),
+            AddonStatus::Outdated { src, dst } => write!(f, "{} is out of date in the target version ({}, the source has {})", self.name, dst, src),
+        }
+    }
+}
+
+// `Name: enabled` lines, in file order
+pub fn parse_addons_txt(input: &str) -> Vec<(String, bool)> {
+    input
+        .lines()
+        .filter_map(|line| line.rsplit_once(':'))
+        .map(|(name, state)| (name.trim().to_owned(), state.trim() == "enabled"))
+        .filter(|(name, _)| !name.is_empty())
+        .collect()
+}
+
+pub fn parse_toc(input: &str) -> Toc {
+    let mut toc = Toc::default();
+    for line in input.lines() {
+        let (key, value) = match line.strip_prefix("##").and_then(|l| l.split_once(':')) {
+            Some((k, v)) => (k.trim(), v.trim()),
+            None => continue,
+        };
+        match key {
+            "Title" => toc.title = Some(value.to_owned()),
+            "Version" => toc.version = Some(value.to_owned()),
+            "Interface" => toc.interface = value
+                .split(',')
+                .filter_map(|v| v.trim().parse().ok())
+                .collect(),
+            _ => (),
+        }
+    }
+    toc
+}
+
+// {install}/{version}/Interface/AddOns
+pub fn addons_dir(version_dir: &Path) -> PathBuf {
+    version_dir.join("Interface").join("AddOns")
+}
+
+// the .toc for an installed addon, or None if it isn't installed. the plain one is used if it's there.
+pub fn read_toc(version_dir: &Path, addon: &str) -> Result<Option<Toc>, Error> {
+    let tocs = toc_paths(version_dir, addon)?;
+    match tocs.first() {
+        Some(path) => Ok(Some(parse_toc(&String::from_utf8_lossy(&fs::read(path)?)))),
+        None => Ok(None),
+    }
+}
+
+// every interface version an installed addon supports, across all its .toc files
+pub fn interfaces(version_dir: &Path, addon: &str) -> Result<Vec<u32>, Error> {
+    let mut interface: Vec<u32> = vec![];
+    for path in toc_paths(version_dir, addon)? {
+        interface.extend(parse_toc(&String::from_utf8_lossy(&fs::read(path)?)).interface);
+    }
+    interface.sort_unstable();
+    interface.dedup();
+    Ok(interface)
+}
+
+// names of every addon folder in a version, sorted
+pub fn installed_addons(version_dir: &Path) -> Result<Vec<String>, Error> {
+    let entries = match fs::read_dir(addons_dir(version_dir)) {
+        Ok(entries) => entries.collect::<Result<Vec<_>, Error>>()?,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(vec![]),
+        Err(e) => return Err(e),
+    };
+
+    let mut names: Vec<String> = entries
+        .iter()
+        .filter(|e| e.path().is_dir())
+        .map(|e| e.file_name().to_string_lossy().into_owned())
+        .collect();
+    names.sort();
+    Ok(names)
+}
+
+// every addon folder in the source version, checked against the destination version
+pub fn compare_addon_folders(src_version_dir: &Path, dst_version_dir: &Path) -> Result<Vec<AddonFolder>, Error> {
+    let flavor = Flavor::of(dst_version_dir.file_name().unwrap_or_default());
+    let mut folders: Vec<AddonFolder> = vec![];
+
+    for name in installed_addons(src_version_dir)? {
+        let interface = interfaces(src_version_dir, &name)?;
+        folders.push(AddonFolder {
+            supported: interface.iter().any(|i| flavor.supports(*i)),
+            status: status(src_version_dir, dst_version_dir, &name)?,
+            interface,
+            name,
+        });
+    }
+
+    Ok(folders)
+}
+
+// checks every addon in a character's AddOns.txt against what's installed in the destination version.
+// Blizzard's own addons ship with the client, so they're left out. so are addons the source version
+// doesn't have either, since the client keeps AddOns.txt entries for addons long after they're removed.
+pub fn check_addons(addons_txt: &Path, src_version_dir: &Path, dst_version_dir: &Path) -> Result<Vec<AddonCheck>, Error> {
+    let mut checks: Vec<AddonCheck> = vec![];
+
+    for (name, enabled) in parse_addons_txt(&String::from_utf8_lossy(&fs::read(addons_txt)?)) {
+        if name.starts_with("Blizzard_") || toc_paths(src_version_dir, &name)?.is_empty() {
+            continue
+        }
+
+        let status = status(src_version_dir, dst_version_dir, &name)?;
+        checks.push(AddonCheck { name, enabled, status });
+    }
+
+    Ok(checks)
+}
+
+fn status(src_version_dir: &Path, dst_version_dir: &Path, addon: &str) -> Result<AddonStatus, Error> {
+    Ok(match read_toc(dst_version_dir, addon)? {
+        None => AddonStatus::Missing,
+        Some(dst) => match (read_toc(src_version_dir, addon)?.and_then(|t| t.version), dst.version) {
+            (Some(src), Some(dst)) if is_older(&dst, &src) => AddonStatus::Outdated { src, dst },
+            _ => AddonStatus::Installed,
+        },
+    })
+}
+
+// an addon's .toc files, the plain one first
+fn toc_paths(version_dir: &Path, addon: &str) -> Result<Vec<PathBuf>, Error> {
+    let mut tocs = match fs::read_dir(addons_dir(version_dir).join(addon)) {
+        Ok(entries) => entries
+            .map(|e| e.map(|e| e.path()))
+            .collect::<Result<Vec<_>, Error>>()?,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(vec![]),
+        Err(e) => return Err(e),
+    };
+    tocs.retain(|p| {
+        p.extension().is_some_and(|e| e.eq_ignore_ascii_case("toc"))
+            && p.file_stem().is_some_and(|s| s.to_string_lossy().starts_with(addon))
+    });
+    tocs.sort_by_key(|p| (p.file_stem() != Some(OsStr::new(addon)), p.clone()));
+    Ok(tocs)
+}
+
+// compares the numbers in two version strings in order, so "5.9.1" is older than "5.17.0".
+// versions without numbers (like "@project-version@" in development checkouts) are never older.
+fn is_older(a: &str, b: &str) -> bool {
+    let numbers = |v: &str| -> Vec<u64> {
+        v.split(|c: char| !c.is_ascii_digit())
+            .filter_map(|n| n.parse().ok())
+            .collect()
+    };
+    let (a, b) = (numbers(a), numbers(b));
+    !a.is_empty() && !b.is_empty() && a < b
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scratch(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("wow-profile-copy-ng-addons-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn toc(version_dir: &Path, addon: &str, file: &str, contents: &str) {
+        let dir = addons_dir(version_dir).join(addon);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join(file), contents).unwrap();
+    }
+
+    #[test]
+    fn addons_txt() {
+        let parsed = parse_addons_txt("WeakAuras: enabled\r\nDetails: disabled\nBlizzard_Deprecated: enabled\n\n: enabled\nnot a line\n");
+        assert_eq!(parsed, [
+            (String::from("WeakAuras"), true),
+            (String::from("Details"), false),
+            (String::from("Blizzard_Deprecated"), true),
+        ]);
+    }
+
+    #[test]
+    fn toc_fields() {
+        let toc = parse_toc("## Interface: 110005, 40401 ,11505\r\n## Title: WeakAuras\n## Version: 5.17.2\n# ## Interface: 1\nWeakAuras.lua\n");
+        assert_eq!(toc, Toc {
+            title: Some(String::from("WeakAuras")),
+            version: Some(String::from("5.17.2")),
+            interface: vec![110005, 40401, 11505],
+        });
+        assert_eq!(parse_toc("## Interface: 110005\n"), Toc { title: None, version: None, interface: vec![110005] });
+    }
+
+    #[test]
+    fn flavors() {
+        assert_eq!(Flavor::of(OsStr::new("_retail_")), Flavor::Mainline);
+        assert_eq!(Flavor::of(OsStr::new("_xptr_")), Flavor::Mainline);
+        assert_eq!(Flavor::of(OsStr::new("_classic_")), Flavor::Classic);
+        assert_eq!(Flavor::of(OsStr::new("_classic_ptr_")), Flavor::Classic);
+        assert_eq!(Flavor::of(OsStr::new("_classic_era_")), Flavor::Vanilla);
+        assert_eq!(Flavor::of(OsStr::new("_classic_era_ptr_")), Flavor::Vanilla);
+
+        assert!(Flavor::Mainline.supports(110005));
+        assert!(!Flavor::Mainline.supports(40401));
+        assert!(Flavor::Classic.supports(40401));
+        assert!(!Flavor::Classic.supports(11505));
+        assert!(Flavor::Vanilla.supports(11505));
+        assert!(!Flavor::Vanilla.supports(110005));
+    }
+
+    #[test]
+    fn versions() {
+        assert!(is_older("5.9.1", "5.17.0"));
+        assert!(!is_older("5.17.0", "5.9.1"));
+        assert!(!is_older("5.17.0", "5.17.0"));
+        assert!(is_older("v5.17", "5.17.1"));
+        assert!(!is_older("@project-version@", "5.17.0"));
+        assert!(!is_older("5.17.0", "@project-version@"));
+    }
+
+    #[test]
+    fn check_against_target() {
+        let dir = scratch("check");
+        let (src, dst) = (dir.join("_retail_"), dir.join("_classic_"));
+        // WeakAuras only has a classic .toc in the target, with several interface versions
+        toc(&src, "WeakAuras", "WeakAuras.toc", "## Interface: 110005\n## Version: 5.17.2\n");
+        toc(&dst, "WeakAuras", "WeakAuras_Cata.toc", "## Interface: 40401, 40400\n## Version: 5.9.1\n");
+        toc(&src, "Details", "Details.toc", "## Interface: 110005\n## Version: 1.0\n");
+        toc(&dst, "Details", "Details.toc", "## Interface: 40401\n## Version: 1.0\n");
+        toc(&src, "Plater", "Plater_Mainline.toc", "## Interface: 110005\n");
+        toc(&src, "Blizzard_Deprecated", "Blizzard_Deprecated.toc", "## Interface: 110005\n");
+        fs::write(dir.join("AddOns.txt"), "WeakAuras: enabled\nDetails: enabled\nPlater: disabled\nBlizzard_Deprecated: enabled\nRemovedLongAgo: enabled\n").unwrap();
+
+        let checks = check_addons(&dir.join("AddOns.txt"), &src, &dst);
+        let interface = interfaces(&dst, "WeakAuras");
+        fs::remove_dir_all(&dir).unwrap();
+
+        // Blizzard's own and ones the source no longer has are left out
+        assert_eq!(checks.unwrap(), [
+            AddonCheck { name: String::from("WeakAuras"), enabled: true, status: AddonStatus::Outdated { src: String::from("5.17.2"), dst: String::from("5.9.1") } },
+            AddonCheck { name: String::from("Details"), enabled: true, status: AddonStatus::Installed },
+            AddonCheck { name: String::from("Plater"), enabled: false, status: AddonStatus::Missing },
+        ]);
+        assert_eq!(interface.unwrap(), [40400, 40401]);
+    }
+}
@@ -0,0 +1,301 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use crate::wow::{Install, Wtf};
+use std::{ffi::OsString, fs, io::{Error, ErrorKind}, path::{Path, PathBuf}, time::{SystemTime, UNIX_EPOCH}};
+
+// snapshots live next to the WTF folder of the version they were taken from,
+// e.g. {install}/_retail_/WTF-Snapshots/{id}
+pub const SNAPSHOT_DIR: &str = "WTF-Snapshots";
+const MANIFEST: &str = "snapshot.txt";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Snapshot {
+    pub dir: PathBuf,
+    pub version: OsString,
+    pub wtf: Wtf,
+    pub created: u64,
+    pub files: Vec<SnapshotFile>,
+}
+
+// a single destination file, relative to the version folder.
+// files that didn't exist before the copy are removed again on restore.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SnapshotFile {
+    pub path: PathBuf,
+    pub existed: bool,
+}
+
+impl std::fmt::Display for Snapshot {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} ({}) - {} - {} files",
+            self.wtf,
+            self.wtf.account.to_str().unwrap_or_default(),
+            format_timestamp(self.created),
+            self.files.len()
+        )
+    }
+}
+
+impl Snapshot {
+    // the version folder it was taken from, which its files are relative to
+    pub fn version_dir(&self) -> Result<&Path, Error> {
+        self.dir.parent()
+            .and_then(|p| p.parent())
+            .ok_or_else(|| Error::other("snapshot isn't inside a version folder"))
+    }
+}
+
+// copies every file in `files` that currently exists into a new snapshot for the given version folder.
+// `files` are absolute destination paths, and must be inside `version_dir`.
+pub fn take_snapshot(version_dir: &Path, wtf: &Wtf, files: &[PathBuf]) -> Result<Snapshot, Error> {
+    let created = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_err(Error::other)?
+        .as_secs();
+
+    let store = version_dir.join(SNAPSHOT_DIR);
+    let base = format!("{}-{}-{}",
+        created,
+        wtf.character.to_str().unwrap_or_default(),
+        wtf.realm.to_str().unwrap_or_default()
+    );
+
+    // two copies in the same second shouldn't clobber each other
+    let mut dir = store.join(&base);
+    let mut n = 1;
+    while dir.try_exists()? {
+        dir = store.join(format!("{}-{}", base, n));
+        n += 1;
+    }
+
+    // it's put together in a hidden folder and only renamed into place once it's complete,
+    // so a failure partway doesn't leave a snapshot that's missing files or its manifest
+    let mut staging = OsString::from(".");
+    staging.push(dir.file_name().unwrap_or_default());
+    staging.push(".partial");
+    let staging = store.join(staging);
+    fs::create_dir_all(&staging)?;
+
+    let mut snapshot = Snapshot {
+        dir: staging,
+        version: version_dir.file_name().unwrap_or_default().to_os_string(),
+        wtf: wtf.clone(),
+        created,
+        files: vec![],
+    };
+    let filled = fill_snapshot(&mut snapshot, version_dir, files)
+        .and_then(|_| write_manifest(&snapshot))
+        .and_then(|_| fs::rename(&snapshot.dir, &dir));
+    if let Err(e) = filled {
+        let _ = fs::remove_dir_all(&snapshot.dir);
+        return Err(e)
+    }
+
+    snapshot.dir = dir;
+    Ok(snapshot)
+}
+
+// copies the files that exist into the snapshot's folder, and lists them all in it
+fn fill_snapshot(snapshot: &mut Snapshot, version_dir: &Path, files: &[PathBuf]) -> Result<(), Error> {
+    for file in files {
+        let rel = file.strip_prefix(version_dir)
+            .map_err(|_| Error::other(format!("{:?} is outside of {:?}", file, version_dir)))?
+            .to_path_buf();
+
+        let existed = file.try_exists()?;
+        if file.is_dir() {
+            return Err(Error::other(format!("destination {} is a folder, not a file", file.display())))
+        }
+        if existed {
+            let dst = snapshot.dir.join(&rel);
+            if let Some(parent) = dst.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::copy(file, &dst).map_err(|e| Error::new(e.kind(), format!("couldn't save {} in the snapshot: {}", file.display(), e)))?;
+        }
+        snapshot.files.push(SnapshotFile { path: rel, existed });
+    }
+    Ok(())
+}
+
+// lists the snapshots of every version in the install, newest first
+pub fn list_snapshots(install: &Install) -> Result<Vec<Snapshot>, Error> {
+    let mut snapshots: Vec<Snapshot> = vec![];
+
+    for ver in &install.versions {
+        let store = PathBuf::from(&install.install_dir).join(&ver.name).join(SNAPSHOT_DIR);
+        let entries = match fs::read_dir(&store) {
+            Ok(entries) => entries.collect::<Result<Vec<_>, Error>>()?,
+            Err(e) if e.kind() == ErrorKind::NotFound => continue,
+            Err(e) => return Err(e),
+        };
+
+        for e in entries {
+            // hidden ones are still being taken
+            if !e.file_type()?.is_dir() || e.file_name().to_string_lossy().starts_with('.') {
+                continue
+            }
+            match read_manifest(&e.path(), &ver.name) {
+                Ok(s) => snapshots.push(s),
+                // half-written or foreign directories aren't worth failing over
+                Err(_) => continue,
+            }
+        }
+    }
+
+    snapshots.sort_by(|a, b| b.created.cmp(&a.created).then(a.dir.cmp(&b.dir)));
+
+    Ok(snapshots)
+}
+
+pub fn delete_snapshot(snapshot: &Snapshot) -> Result<(), Error> {
+    fs::remove_dir_all(&snapshot.dir)
+}
+
+// manifest format, one entry per line:
+//   created <unix seconds>
+//   account|realm|character <name>
+//   saved|new <path relative to the version folder>
+fn write_manifest(snapshot: &Snapshot) -> Result<(), Error> {
+    let mut lines: Vec<String> = vec![
+        format!("created {}", snapshot.created),
+        format!("account {}", snapshot.wtf.account.to_string_lossy()),
+        format!("realm {}", snapshot.wtf.realm.to_string_lossy()),
+        format!("character {}", snapshot.wtf.character.to_string_lossy()),
+    ];
+    for file in &snapshot.files {
+        lines.push(format!("{} {}",
+            if file.existed {"saved"} else {"new"},
+            file.path.to_string_lossy()
+        ));
+    }
+
+    fs::write(snapshot.dir.join(MANIFEST), lines.join("\n") + "\n")
+}
+
+fn read_manifest(dir: &Path, version: &OsString) -> Result<Snapshot, Error> {
+    let contents = fs::read_to_string(dir.join(MANIFEST))?;
+
+    let mut snapshot = Snapshot {
+        dir: dir.to_path_buf(),
+        version: version.clone(),
+        wtf: Wtf::default(),
+        created: 0,
+        files: vec![],
+    };
+
+    for line in contents.lines() {
+        let (key, value) = match line.split_once(' ') {
+            Some(kv) => kv,
+            None => continue,
+        };
+        match key {
+            "created" => snapshot.created = value.parse().map_err(Error::other)?,
+            "account" => snapshot.wtf.account = OsString::from(value),
+            "realm" => snapshot.wtf.realm = OsString::from(value),
+            "character" => snapshot.wtf.character = OsString::from(value),
+            "saved" | "new" => snapshot.files.push(SnapshotFile {
+                path: PathBuf::from(value),
+                existed: key == "saved",
+            }),
+            _ => continue,
+        }
+    }
+
+    Ok(snapshot)
+}
+
+// formats unix seconds as "YYYY-MM-DD HH:MM:SS UTC"
+pub fn format_timestamp(secs: u64) -> String {
+    let days = (secs / 86400) as i64;
+    let rem = secs % 86400;
+
+    // civil-from-days, see http://howardhinnant.github.io/date_algorithms.html
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    format!("{:04}-{:02}-{:02} {:02}:{:02}:{:02} UTC",
+        year, month, day,
+        rem / 3600, (rem % 3600) / 60, rem % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wow::Version;
+
+    fn scratch(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("wow-profile-copy-ng-backup-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn snapshot_and_list() {
+        let dir = scratch("list");
+        let version = dir.join("_retail_");
+        let account = version.join("WTF").join("Account").join("ACC");
+        fs::create_dir_all(&account).unwrap();
+        fs::write(account.join("config-cache.wtf"), "SET a \"1\"\n").unwrap();
+        let wtf = Wtf {
+            account: OsString::from("ACC"),
+            realm: OsString::from("Area 52"),
+            character: OsString::from("Main"),
+            has_vars: false,
+        };
+
+        let snapshot = take_snapshot(&version, &wtf, &[account.join("config-cache.wtf"), account.join("macros-cache.txt")]).unwrap();
+        // one still being taken, and a folder that isn't a snapshot
+        fs::create_dir_all(version.join(SNAPSHOT_DIR).join(".1-Alt-Area 52.partial")).unwrap();
+        fs::create_dir_all(version.join(SNAPSHOT_DIR).join("something else")).unwrap();
+        let saved = fs::read_to_string(snapshot.dir.join("WTF/Account/ACC/config-cache.wtf")).unwrap();
+        let listed = list_snapshots(&Install {
+            install_dir: dir.clone().into_os_string(),
+            versions: vec![Version { name: OsString::from("_retail_"), wtfs: vec![] }, Version { name: OsString::from("_ptr_"), wtfs: vec![] }],
+        }).unwrap();
+        let version_dir = snapshot.version_dir().unwrap().to_path_buf();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(saved, "SET a \"1\"\n");
+        assert_eq!(snapshot.files, [
+            SnapshotFile { path: PathBuf::from("WTF/Account/ACC/config-cache.wtf"), existed: true },
+            SnapshotFile { path: PathBuf::from("WTF/Account/ACC/macros-cache.txt"), existed: false },
+        ]);
+        assert_eq!(listed, [snapshot]);
+        assert_eq!(version_dir, version);
+    }
+
+    #[test]
+    fn file_outside_version() {
+        let dir = scratch("outside");
+        let version = dir.join("_retail_");
+        fs::create_dir_all(&version).unwrap();
+        let taken = take_snapshot(&version, &Wtf::default(), &[dir.join("elsewhere.txt")]);
+        let left = fs::read_dir(version.join(SNAPSHOT_DIR)).unwrap().count();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert!(taken.is_err());
+        // the half-taken snapshot is removed
+        assert_eq!(left, 0);
+    }
+
+    #[test]
+    fn timestamps() {
+        assert_eq!(format_timestamp(0), "1970-01-01 00:00:00 UTC");
+        assert_eq!(format_timestamp(951782400), "2000-02-29 00:00:00 UTC");
+        assert_eq!(format_timestamp(1700000000), "2023-11-14 22:13:20 UTC");
+    }
+}
@@ -0,0 +1,213 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+// bindings-cache.wtf files, which hold key bindings one per line:
+//
+//   BINDINGMODE 0
+//   bind SHIFT-1 ACTIONBUTTON1
+//   bind CTRL-F CLICK MyAddonButton:LeftButton
+//
+// keys can have ALT-, CTRL-, SHIFT- and META- in front of them, and each combination of
+// those is a layer of its own, e.g. everything on SHIFT.
+
+use std::{collections::BTreeSet, fs, io::Error, path::Path};
+
+const MODIFIERS: [&str; 4] = ["ALT", "CTRL", "SHIFT", "META"];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BindingLine {
+    Bind { key: String, action: String },
+    // BINDINGMODE and anything else, kept so the file can be written back as it was
+    Other(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct BindingsCache {
+    pub lines: Vec<BindingLine>,
+    crlf: bool,
+}
+
+// a key bound to one thing in the source and something else in the destination
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Conflict {
+    pub key: String,
+    pub src: String,
+    pub dst: String,
+}
+
+impl std::fmt::Display for Conflict {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} is {} in the source but {} on the target", self.key, self.src, self.dst)
+    }
+}
+
+impl BindingsCache {
+    pub fn parse(input: &str) -> BindingsCache {
+        let lines = input
+            .lines()
+            .map(|line| match parse_bind(line) {
+                Some((key, action)) => BindingLine::Bind { key, action },
+                None => BindingLine::Other(line.to_owned()),
+            })
+            .collect();
+
+        BindingsCache { lines, crlf: input.contains("\r\n") }
+    }
+
+    pub fn read(path: &Path) -> Result<BindingsCache, Error> {
+        Ok(BindingsCache::parse(&String::from_utf8_lossy(&fs::read(path)?)))
+    }
+
+    // every binding, in file order
+    pub fn binds(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.lines.iter().filter_map(|l| match l {
+            BindingLine::Bind { key, action } => Some((key.as_str(), action.as_str())),
+            BindingLine::Other(_) => None,
+        })
+    }
+
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.binds()
+            .find(|(k, _)| same_key(k, key))
+            .map(|(_, a)| a)
+    }
+
+    // rebinds an existing key, or adds it to the end
+    pub fn set(&mut self, key: &str, action: &str) {
+        for line in self.lines.iter_mut() {
+            if let BindingLine::Bind { key: k, action: a } = line {
+                if same_key(k, key) {
+                    *a = action.to_owned();
+                    return
+                }
+            }
+        }
+        self.lines.push(BindingLine::Bind { key: key.to_owned(), action: action.to_owned() });
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let newline = if self.crlf {"\r\n"} else {"\n"};
+        let mut out = String::new();
+        for line in &self.lines {
+            match line {
+                BindingLine::Bind { key, action } => out += &format!("bind {} {}", key, action),
+                BindingLine::Other(s) => out += s,
+            }
+            out += newline;
+        }
+        out.into_bytes()
+    }
+}
+
+// the modifiers held down for a key, e.g. "CTRL-SHIFT" for CTRL-SHIFT-1, or "" for none
+pub fn layer(key: &str) -> String {
+    split_key(key).0.join("-")
+}
+
+// every key bound differently on each side
+pub fn conflicts(dst: &BindingsCache, src: &BindingsCache) -> Vec<Conflict> {
+    src.binds()
+        .filter_map(|(key, action)| match dst.get(key) {
+            Some(d) if d != action => Some(Conflict { key: key.to_owned(), src: action.to_owned(), dst: d.to_owned() }),
+            _ => None,
+        })
+        .collect()
+}
+
+// copies only the given keys' bindings from src into dst, leaving the rest of dst as it is.
+// returns the merged file and the bindings in dst that were replaced by something different.
+pub fn merge(dst: &BindingsCache, src: &BindingsCache, keys: &BTreeSet<String>) -> (BindingsCache, Vec<Conflict>) {
+    let picked = |key: &str| keys.iter().any(|k| same_key(k, key));
+    let replaced = conflicts(dst, src).into_iter().filter(|c| picked(&c.key)).collect();
+
+    let mut merged = dst.clone();
+    for (key, action) in src.binds() {
+        if picked(key) {
+            merged.set(key, action);
+        }
+    }
+    (merged, replaced)
+}
+
+// modifiers can be written in any order, so SHIFT-CTRL-1 and CTRL-SHIFT-1 are the same key
+fn same_key(a: &str, b: &str) -> bool {
+    let (mut a_mods, a_key) = split_key(a);
+    let (mut b_mods, b_key) = split_key(b);
+    a_mods.sort_unstable();
+    b_mods.sort_unstable();
+    a_key.eq_ignore_ascii_case(b_key) && a_mods == b_mods
+}
+
+fn split_key(key: &str) -> (Vec<&str>, &str) {
+    let mut mods: Vec<&str> = vec![];
+    let mut rest = key;
+    while let Some((m, after)) = rest.split_once('-') {
+        // typed in by hand, modifiers can be lowercase
+        let m = match MODIFIERS.iter().find(|known| known.eq_ignore_ascii_case(m)) {
+            Some(known) if !after.is_empty() => known,
+            _ => break,
+        };
+        mods.push(m);
+        rest = after;
+    }
+    (mods, rest)
+}
+
+// `bind KEY ACTION`, where the action can have spaces in it
+fn parse_bind(line: &str) -> Option<(String, String)> {
+    let rest = line.strip_prefix("bind ")?;
+    let (key, action) = rest.split_once(' ')?;
+    Some((key.to_owned(), action.trim_end().to_owned()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MAIN: &str = "BINDINGMODE 0\r\nbind 1 ACTIONBUTTON1\r\nbind SHIFT-1 ACTIONBUTTON1\r\nbind CTRL-SHIFT-F CLICK WeakAurasButton:LeftButton\r\nbind SHIFT-- MULTIACTIONBAR1BUTTON12\r\nbind , TOGGLEWORLDMAP\r\n";
+    const ALT: &str = "BINDINGMODE 0\r\nbind 1 ACTIONBUTTON1\r\nbind SHIFT-1 TARGETNEARESTENEMY\r\nbind SHIFT-CTRL-F TOGGLEFPS\r\n";
+
+    #[test]
+    fn same_key_ignores_modifier_order() {
+        assert!(same_key("CTRL-SHIFT-F", "SHIFT-CTRL-F"));
+        assert!(same_key("shift-1", "SHIFT-1"));
+        assert!(!same_key("SHIFT-1", "1"));
+        assert!(!same_key("CTRL-1", "ALT-1"));
+        // a trailing - is the minus key, not a modifier
+        assert!(same_key("SHIFT--", "SHIFT--"));
+        assert!(!same_key("SHIFT--", "SHIFT-"));
+        assert_eq!(layer("SHIFT--"), "SHIFT");
+        assert_eq!(layer("-"), "");
+        assert_eq!(layer("CTRL-SHIFT-F"), "CTRL-SHIFT");
+    }
+
+    #[test]
+    fn round_trip() {
+        assert_eq!(BindingsCache::parse(MAIN).to_bytes(), MAIN.as_bytes());
+        assert_eq!(BindingsCache::parse(MAIN).get(","), Some("TOGGLEWORLDMAP"));
+        assert_eq!(BindingsCache::parse(MAIN).get("CTRL-SHIFT-F"), Some("CLICK WeakAurasButton:LeftButton"));
+    }
+
+    #[test]
+    fn merge_picked_keys() {
+        let keys = BTreeSet::from([String::from("SHIFT-1"), String::from("CTRL-SHIFT-F"), String::from(",")]);
+        let (merged, replaced) = merge(&BindingsCache::parse(ALT), &BindingsCache::parse(MAIN), &keys);
+
+        // keys already on the target are rebound where they are, written the target's way, and new ones go at the end
+        assert_eq!(String::from_utf8(merged.to_bytes()).unwrap(), "BINDINGMODE 0\r\nbind 1 ACTIONBUTTON1\r\nbind SHIFT-1 ACTIONBUTTON1\r\nbind SHIFT-CTRL-F CLICK WeakAurasButton:LeftButton\r\nbind , TOGGLEWORLDMAP\r\n");
+        assert_eq!(replaced, vec![
+            Conflict { key: String::from("SHIFT-1"), src: String::from("ACTIONBUTTON1"), dst: String::from("TARGETNEARESTENEMY") },
+            Conflict { key: String::from("CTRL-SHIFT-F"), src: String::from("CLICK WeakAurasButton:LeftButton"), dst: String::from("TOGGLEFPS") },
+        ]);
+    }
+
+    #[test]
+    fn merge_nothing_picked() {
+        let (merged, replaced) = merge(&BindingsCache::parse(ALT), &BindingsCache::parse(MAIN), &BTreeSet::new());
+        assert_eq!(merged.to_bytes(), ALT.as_bytes());
+        assert!(replaced.is_empty());
+    }
+}
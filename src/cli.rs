@@ -0,0 +1,465 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use wow_profile_copy_ng::client;
+use wow_profile_copy_ng::config;
+use wow_profile_copy_ng::settings::{self, Preset};
+use wow_profile_copy_ng::copy::{self, CopyRequest, Counts, Scope};
+use wow_profile_copy_ng::error::Error;
+use wow_profile_copy_ng::wow::{self, Install, Version, Wtf};
+use std::{collections::{BTreeMap, BTreeSet}, ffi::OsString, fs, path::{Path, PathBuf}, process::ExitCode};
+
+const USAGE: &str = "\
+usage:
+  wow-profile-copy-ng                     start the graphical interface
+  wow-profile-copy-ng list [options]      list versions and characters in an install
+  wow-profile-copy-ng copy --from <character> --to <character>... [options]
+  wow-profile-copy-ng presets             list the presets saved in the graphical interface
+  wow-profile-copy-ng run <preset> [--dry-run] [--allow-running] [--report <file>]
+                                          run a saved preset
+
+characters are given as <version>/<account>/<realm>/<character>, where
+<version> is either the folder name (_retail_) or its display name (Retail).
+--to can be given more than once to copy to several characters.
+
+options:
+  --install <dir>           WoW installation folder (defaults to the first one found in
+                            the usual location, or in a Wine, Proton, Lutris, Bottles
+                            or Heroic prefix on Linux)
+  --to-install <dir>        WoW installation folder the --to characters are in, when
+                            copying from one install to another (defaults to --install)
+  --no-overwrite-account    don't copy account-level configuration and variables
+  --skip <addon>            don't copy this addon's SavedVariables, can be repeated
+  --account-cvars <names>   only copy these comma separated CVars into the account's
+                            config-cache.wtf, keeping the rest of the target's
+  --character-cvars <names> the same for the character's config-cache.wtf
+  --keep-cvar <name>        never copy this CVar, the target keeps its own value. can be
+                            repeated, and a trailing * matches every CVar starting with it.
+                            display and graphics CVars like gxWindow are kept by default
+  --copy-all-cvars          copy display and graphics CVars too
+  --bindings <keys>         only copy the bindings for these comma separated keys
+                            (e.g. SHIFT-1,CTRL-F), keeping the rest of the target's
+  --account-macros <names>  only copy these comma separated general macros, replacing
+                            ones with the same name and adding the rest if there's room
+  --character-macros <names>
+                            the same for the character's own macros
+  --sync-addons <names>     copy these comma separated addon folders from the source
+                            version's Interface/AddOns to the target version's
+                            (a comma that's part of a name in these lists is written \\,
+                            like SHIFT-\\, for the , key)
+  --dry-run                 print what would be copied without changing anything
+  --allow-running           copy even if WoW looks like it's running from the target
+                            version, which will write over the copy when it exits
+  --report <file>           also write what was done to each file, with full paths, to <file>";
+
+// everything the command line asked for
+#[derive(Debug, Default, PartialEq, Eq)]
+struct Args {
+    command: String,
+    // the preset to run, for the run command
+    preset: Option<String>,
+    install_dir: Option<OsString>,
+    to_install_dir: Option<OsString>,
+    from: Option<String>,
+    to: Vec<String>,
+    overwrite_account: bool,
+    dry_run: bool,
+    report: Option<PathBuf>,
+    allow_running: bool,
+    skip: Vec<String>,
+    merge_cvars: BTreeMap<Scope, BTreeSet<String>>,
+    keep_cvars: BTreeSet<String>,
+    copy_all_cvars: bool,
+    merge_bindings: Option<BTreeSet<String>>,
+    merge_macros: BTreeMap<Scope, BTreeSet<String>>,
+    sync_addons: BTreeSet<String>,
+}
+
+// whether the first argument is one of the commands, rather than something the system passed along
+// (macOS gives apps started from Finder a -psn_... argument)
+pub fn is_command(arg: &str) -> bool {
+    matches!(arg, "list" | "copy" | "presets" | "run" | "help" | "--help" | "-h")
+}
+
+// exit codes: 0 on success, 1 if the copy failed or partially failed, or anything else went wrong
+// (no install found, the report couldn't be written), 2 for bad arguments
+pub fn run(args: Vec<String>) -> ExitCode {
+    if matches!(args.first().map(String::as_str), Some("help" | "--help" | "-h")) {
+        println!("{}", USAGE);
+        return ExitCode::SUCCESS
+    }
+    let Args {
+        command, preset, install_dir, to_install_dir, from, to, overwrite_account, dry_run, report, allow_running,
+        skip, merge_cvars, mut keep_cvars, copy_all_cvars, merge_bindings, merge_macros, sync_addons,
+    } = match parse(args) {
+        Ok(args) => args,
+        Err(message) => return usage(&message),
+    };
+
+    // presets know their own installs
+    match (command.as_str(), preset) {
+        ("presets", _) => return list_presets(),
+        ("run", Some(name)) => return run_preset(&name, dry_run, allow_running, report.as_deref()),
+        _ => (),
+    }
+
+    let install = match install_dir {
+        Some(dir) => match open_install(dir) {
+            Ok(install) => install,
+            Err(code) => return code,
+        },
+        None => match wow::find_installs().into_iter().next() {
+            Some(install) => install,
+            None => {
+                eprintln!("couldn't find a WoW install, use --install");
+                return ExitCode::FAILURE
+            },
+        },
+    };
+    let dst_install = match to_install_dir {
+        Some(dir) => match open_install(dir) {
+            Ok(install) => install,
+            Err(code) => return code,
+        },
+        None => install.clone(),
+    };
+
+    match command.as_str() {
+        "list" => {
+            list(&install);
+            ExitCode::SUCCESS
+        },
+        "copy" => {
+            let from = match from {
+                Some(from) if !to.is_empty() => from,
+                _ => return usage("copy needs both --from and --to"),
+            };
+            let (src_ver, src_wtf) = match find_character(&install, &from) {
+                Ok(c) => c,
+                Err(e) => return usage(&e),
+            };
+            let skip_savedvars = match copy::list_saved_variables(install.install_dir.as_ref(), &src_ver.name, &src_wtf) {
+                Ok(files) => files.into_iter().filter(|f| skip.contains(&f.addon())).collect(),
+                Err(e) => {
+                    eprintln!("error listing SavedVariables: {}", e);
+                    return ExitCode::FAILURE
+                }
+            };
+
+            if !copy_all_cvars {
+                keep_cvars.extend(config::default_keep_cvars());
+            }
+
+            let mut reqs: Vec<CopyRequest> = vec![];
+            for t in &to {
+                let (dst_ver, dst_wtf) = match find_character(&dst_install, t) {
+                    Ok(c) => c,
+                    Err(e) => return usage(&e),
+                };
+                let mut req = CopyRequest::new(&install.install_dir, &src_ver.name, src_wtf.clone(), dst_ver.name, dst_wtf);
+                req.dst_install_dir = PathBuf::from(&dst_install.install_dir);
+                req.overwrite_account = overwrite_account;
+                req.skip_savedvars.clone_from(&skip_savedvars);
+                req.merge_cvars.clone_from(&merge_cvars);
+                req.keep_cvars.clone_from(&keep_cvars);
+                req.merge_bindings.clone_from(&merge_bindings);
+                req.merge_macros.clone_from(&merge_macros);
+                req.sync_addons.clone_from(&sync_addons);
+                reqs.push(req);
+            }
+            copy_characters(&reqs, dry_run, allow_running, report.as_deref())
+        },
+        _ => usage(&format!("unknown command: {}", command)),
+    }
+}
+
+// reads the arguments, or says what's wrong with them
+fn parse(args: Vec<String>) -> Result<Args, String> {
+    let mut args = args.into_iter();
+    let mut parsed = Args {
+        command: args.next().unwrap_or_default(),
+        overwrite_account: true,
+        ..Args::default()
+    };
+
+    // the one command that takes something other than options
+    if parsed.command == "run" {
+        match args.next() {
+            Some(name) => parsed.preset = Some(name),
+            None => return Err(String::from("run needs a preset name")),
+        }
+    }
+
+    // options that choose what to copy, which a preset already has
+    let mut copy_options: Vec<String> = vec![];
+
+    while let Some(arg) = args.next() {
+        if !matches!(arg.as_str(), "--dry-run" | "--allow-running" | "--report") {
+            copy_options.push(arg.clone());
+        }
+        let mut value = |what: &str| args.next().ok_or_else(|| format!("{} needs {}", arg, what));
+        match arg.as_str() {
+            "--install" => parsed.install_dir = Some(OsString::from(value("a folder")?)),
+            "--to-install" => parsed.to_install_dir = Some(OsString::from(value("a folder")?)),
+            "--from" => parsed.from = Some(value("a character")?),
+            "--to" => parsed.to.push(value("a character")?),
+            "--no-overwrite-account" => parsed.overwrite_account = false,
+            "--dry-run" => parsed.dry_run = true,
+            "--allow-running" => parsed.allow_running = true,
+            "--report" => parsed.report = Some(PathBuf::from(value("a file")?)),
+            "--skip" => parsed.skip.push(value("an addon name")?),
+            "--keep-cvar" => {
+                parsed.keep_cvars.insert(value("a CVar name")?);
+            },
+            "--copy-all-cvars" => parsed.copy_all_cvars = true,
+            "--bindings" => {
+                let keys = settings::parse_list(&value("a list of keys")?);
+                parsed.merge_bindings.get_or_insert_with(BTreeSet::new).extend(keys);
+            },
+            "--sync-addons" => parsed.sync_addons.extend(settings::parse_list(&value("a list of addon folders")?)),
+            "--account-macros" | "--character-macros" => {
+                let scope = if arg == "--account-macros" {Scope::Account} else {Scope::Character};
+                let names = settings::parse_list(&value("a list of macro names")?);
+                parsed.merge_macros.entry(scope).or_default().extend(names);
+            },
+            "--account-cvars" | "--character-cvars" => {
+                let scope = if arg == "--account-cvars" {Scope::Account} else {Scope::Character};
+                let names = settings::parse_list(&value("a list of CVars")?);
+                parsed.merge_cvars.entry(scope).or_default().extend(names);
+            },
+            _ => return Err(format!("unknown argument: {}", arg)),
+        }
+    }
+
+    if parsed.command == "run" && !copy_options.is_empty() {
+        return Err(format!("run only takes --dry-run, --allow-running and --report, the preset says what to copy: {}", copy_options.join(" ")))
+    }
+
+    Ok(parsed)
+}
+
+fn usage(message: &str) -> ExitCode {
+    eprintln!("{}\n\n{}", message, USAGE);
+    ExitCode::from(2)
+}
+
+fn list_presets() -> ExitCode {
+    match Preset::load_all() {
+        Ok(presets) => {
+            for preset in presets {
+                println!("{}: {}", preset.name, preset.describe());
+            }
+            ExitCode::SUCCESS
+        },
+        Err(e) => {
+            eprintln!("error reading presets: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run_preset(name: &str, dry_run: bool, allow_running: bool, report: Option<&Path>) -> ExitCode {
+    let presets = match Preset::load_all() {
+        Ok(p) => p,
+        Err(e) => {
+            eprintln!("error reading presets: {}", e);
+            return ExitCode::FAILURE
+        }
+    };
+    let preset = match presets.iter().find(|p| p.name == name) {
+        Some(p) => p,
+        None => return usage(&format!("no preset named {:?}, see the presets command", name)),
+    };
+    match preset.requests() {
+        Ok(reqs) => copy_characters(&reqs, dry_run, allow_running, report),
+        Err(e) => {
+            eprintln!("can't run preset {:?}: {}", name, e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn open_install(dir: OsString) -> Result<Install, ExitCode> {
+    wow::get_wow_install(dir.clone()).map_err(|e| {
+        match e {
+            Error::NotAnInstall(_) | Error::NoAccounts(_) => eprintln!("{}", e),
+            _ => eprintln!("can't read {:?}: {}", dir, e),
+        }
+        ExitCode::FAILURE
+    })
+}
+
+fn list(install: &Install) {
+    println!("Install: {}", install.install_dir.to_string_lossy());
+    for ver in &install.versions {
+        println!("{} ({})", ver, ver.name.to_string_lossy());
+        for wtf in &ver.wtfs {
+            println!("  {}/{}/{}/{}{}",
+                ver.name.to_string_lossy(),
+                wtf.account.to_string_lossy(),
+                wtf.realm.to_string_lossy(),
+                wtf.character.to_string_lossy(),
+                if wtf.has_vars {""} else {" (no SavedVariables)"}
+            );
+        }
+    }
+}
+
+// `report` is where to write the report as a file, if anywhere
+fn copy_characters(reqs: &[CopyRequest], dry_run: bool, allow_running: bool, report: Option<&Path>) -> ExitCode {
+    let plans = match copy::plan_batch(reqs) {
+        Ok(p) => p,
+        Err(e) => {
+            eprintln!("error planning copy: {}", e);
+            return ExitCode::FAILURE
+        }
+    };
+
+    if dry_run {
+        for plan in &plans {
+            if plans.len() > 1 {
+                println!("{}", plan.header());
+            }
+            for line in plan.lines() {
+                println!("{}", line);
+            }
+        }
+        return ExitCode::SUCCESS
+    }
+
+    if !allow_running {
+        if let Err(e) = client::check_closed(&plans) {
+            eprintln!("{}\npass --allow-running to copy anyway", e);
+            return ExitCode::FAILURE
+        }
+    }
+
+    let reports = match plans.as_slice() {
+        [plan] => match copy::execute_plan(plan) {
+            Ok(report) => vec![report],
+            Err(e) => {
+                eprintln!("error copying: {}", e);
+                return ExitCode::FAILURE
+            }
+        },
+        _ => copy::execute_batch(&plans),
+    };
+
+    let counts = Counts::of(&reports);
+    println!("{}", counts);
+    for report in &reports {
+        if reports.len() > 1 {
+            println!("{}", report.header);
+        }
+        for line in report.lines() {
+            println!("{}", line);
+        }
+    }
+    // the copy is done either way, but whoever asked for the report is counting on it
+    let mut code = ExitCode::SUCCESS;
+    if let Some(file) = report {
+        if let Err(e) = fs::write(file, copy::report_text(&reports)) {
+            eprintln!("error writing report to {:?}: {}", file.as_os_str(), e);
+            code = ExitCode::FAILURE;
+        }
+    }
+
+    if counts.failed > 0 {
+        eprintln!("{}", Error::PartialCopy { failed: counts.failed, total: counts.done + counts.failed + counts.skipped, not_undone: counts.not_undone });
+        code = ExitCode::FAILURE;
+    }
+    code
+}
+
+// resolves "<version>/<account>/<realm>/<character>" against the install
+fn find_character(install: &Install, spec: &str) -> Result<(Version, Wtf), String> {
+    let parts: Vec<&str> = spec.split('/').collect();
+    if parts.len() != 4 {
+        return Err(format!("expected <version>/<account>/<realm>/<character>, got {:?}", spec))
+    }
+
+    let ver = install.versions
+        .iter()
+        .find(|v| v.name == parts[0] || v.to_string().eq_ignore_ascii_case(parts[0]))
+        .ok_or_else(|| format!("no version {:?} in this install", parts[0]))?;
+
+    let wtf = ver.wtfs
+        .iter()
+        .find(|w| w.account == parts[1] && w.realm == parts[2] && w.character == parts[3])
+        .ok_or_else(|| format!("no character {:?} in {}", spec, ver))?;
+
+    Ok((ver.clone(), wtf.clone()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(line: &str) -> Vec<String> {
+        line.split(' ').map(String::from).collect()
+    }
+
+    #[test]
+    fn only_commands_start_the_cli() {
+        assert!(is_command("copy"));
+        assert!(is_command("--help"));
+        assert!(!is_command("-psn_0_1234567"));
+        assert!(!is_command(""));
+    }
+
+    #[test]
+    fn options() {
+        let parsed = parse(args(r"copy --install /games/wow --from _retail_/A/R/Main --to _ptr_/A/R/Main --to _ptr_/A/R/Alt --skip WeakAuras --bindings SHIFT-\,,CTRL-F --no-overwrite-account")).unwrap();
+        assert_eq!(parsed, Args {
+            command: String::from("copy"),
+            install_dir: Some(OsString::from("/games/wow")),
+            from: Some(String::from("_retail_/A/R/Main")),
+            to: vec![String::from("_ptr_/A/R/Main"), String::from("_ptr_/A/R/Alt")],
+            skip: vec![String::from("WeakAuras")],
+            merge_bindings: Some(BTreeSet::from([String::from("SHIFT-,"), String::from("CTRL-F")])),
+            overwrite_account: false,
+            ..Args::default()
+        });
+
+        let parsed = parse(args("run Raid --dry-run --report out.txt")).unwrap();
+        assert_eq!((parsed.preset, parsed.dry_run, parsed.report), (Some(String::from("Raid")), true, Some(PathBuf::from("out.txt"))));
+    }
+
+    #[test]
+    fn bad_arguments() {
+        let cases = [
+            ("copy --from", "--from needs a character"),
+            ("copy --to _ptr_/A/R/Main --install", "--install needs a folder"),
+            ("copy --from x --character-macros", "--character-macros needs a list of macro names"),
+            ("copy --frm x", "unknown argument: --frm"),
+            ("run", "run needs a preset name"),
+            ("run Raid --skip WeakAuras", "run only takes --dry-run, --allow-running and --report, the preset says what to copy: --skip"),
+        ];
+        for (line, message) in cases {
+            assert_eq!(parse(args(line)), Err(String::from(message)), "{}", line);
+        }
+    }
+
+    #[test]
+    fn characters() {
+        let wtf = Wtf {
+            account: OsString::from("ACC"),
+            realm: OsString::from("Area 52"),
+            character: OsString::from("Main"),
+            has_vars: true,
+        };
+        let install = Install {
+            install_dir: OsString::from("/games/wow"),
+            versions: vec![Version { name: OsString::from("_retail_"), wtfs: vec![wtf.clone()] }],
+        };
+
+        assert_eq!(find_character(&install, "_retail_/ACC/Area 52/Main").map(|(_, w)| w), Ok(wtf.clone()));
+        assert_eq!(find_character(&install, "retail/ACC/Area 52/Main").map(|(_, w)| w), Ok(wtf));
+        assert_eq!(find_character(&install, "_ptr_/ACC/Area 52/Main").unwrap_err(), "no version \"_ptr_\" in this install");
+        assert_eq!(find_character(&install, "Retail/ACC/Area 52/Alt").unwrap_err(), "no character \"Retail/ACC/Area 52/Alt\" in Retail");
+        assert_eq!(find_character(&install, "Main").unwrap_err(), "expected <version>/<account>/<realm>/<character>, got \"Main\"");
+    }
+}
@@ -0,0 +1,203 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+// finding WoW clients that are running, since the game writes its settings over the WTF folder when it
+// exits and would undo a copy made while it was open. only Linux is checked for now, through /proc.
+//
+// under Wine the command line has the Windows path the game was started from:
+//
+//   C:\Program Files (x86)\World of Warcraft\_retail_\Wow.exe
+//
+// which is turned back into a Linux path through the Wine prefix it runs in: C: is the prefix's drive_c,
+// Z: is the root of the filesystem, and any other drive is a link in the prefix's dosdevices folder.
+
+use crate::copy::CopyPlan;
+use crate::error::Error;
+use std::{ffi::OsString, fs, path::{Component, Path, PathBuf}};
+
+const CLIENT_EXES: [&str; 6] = ["Wow.exe", "WowT.exe", "WowB.exe", "WowClassic.exe", "WowClassicT.exe", "WowClassicB.exe"];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RunningClient {
+    pub pid: u32,
+    pub exe: String,
+    // the version folder it was started from, e.g. _retail_
+    pub version: OsString,
+}
+
+impl std::fmt::Display for RunningClient {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} in {} (process {})", self.exe, self.version.to_string_lossy(), self.pid)
+    }
+}
+
+// clients started from an install. processes that can't be read, like other users' when /proc is
+// mounted with hidepid, are left out.
+pub fn running_clients(install_dir: &Path) -> Vec<RunningClient> {
+    if !cfg!(target_os = "linux") {
+        return vec![]
+    }
+    let entries = match fs::read_dir("/proc") {
+        Ok(entries) => entries,
+        Err(_) => return vec![],
+    };
+    let install_dir = fs::canonicalize(install_dir).unwrap_or_else(|_| install_dir.to_path_buf());
+
+    let mut clients: Vec<RunningClient> = vec![];
+    for entry in entries.flatten() {
+        let pid = match entry.file_name().to_str().and_then(|n| n.parse().ok()) {
+            Some(pid) => pid,
+            None => continue,
+        };
+        let cmdline = match fs::read(entry.path().join("cmdline")) {
+            Ok(c) => c,
+            Err(_) => continue,
+        };
+        // Wine uses ~/.wine when WINEPREFIX isn't set
+        let environ: Vec<String> = nul_separated(&fs::read(entry.path().join("environ")).unwrap_or_default()).collect();
+        let prefix = environ.iter()
+            .find_map(|v| v.strip_prefix("WINEPREFIX=").map(PathBuf::from))
+            .or_else(|| environ.iter().find_map(|v| v.strip_prefix("HOME=").map(|h| Path::new(h).join(".wine"))))
+            .map(|p| fs::canonicalize(&p).unwrap_or(p));
+
+        for arg in nul_separated(&cmdline) {
+            if let Some((exe, version)) = client_in(&arg, &install_dir, prefix.as_deref()) {
+                clients.push(RunningClient { pid, exe, version });
+                break
+            }
+        }
+    }
+    clients.sort_by_key(|c| c.pid);
+    clients
+}
+
+// errors if a client is running from any version folder the plans copy to
+pub fn check_closed(plans: &[CopyPlan]) -> Result<(), Error> {
+    let mut installs: Vec<&Path> = plans.iter().filter_map(|p| p.dst_version.parent()).collect();
+    installs.sort();
+    installs.dedup();
+
+    let mut clients: Vec<RunningClient> = vec![];
+    for install in installs {
+        clients.extend(running_clients(install).into_iter().filter(|c| {
+            // Windows paths can be in any case
+            plans.iter().any(|p| p.dst_version.parent() == Some(install)
+                && p.dst_version.file_name().is_some_and(|v| v.eq_ignore_ascii_case(&c.version)))
+        }));
+    }
+
+    match clients.is_empty() {
+        true => Ok(()),
+        false => Err(Error::ClientRunning(clients)),
+    }
+}
+
+// the exe and version folder, if `arg` is the path to a client in the install
+fn client_in(arg: &str, install_dir: &Path, prefix: Option<&Path>) -> Option<(String, OsString)> {
+    let mut parts: Vec<&str> = arg.split(['/', '\\']).filter(|p| !p.is_empty()).collect();
+    let exe = parts.pop()?;
+    if !CLIENT_EXES.iter().any(|e| e.eq_ignore_ascii_case(exe)) {
+        return None
+    }
+    let version = parts.pop()?;
+
+    let dir = if arg.starts_with('/') {
+        Path::new("/").join(parts.join("/"))
+    } else {
+        let drive = parts.first().filter(|d| d.len() == 2 && d.ends_with(':'))?.to_lowercase();
+        drive_root(&drive, prefix)?.join(parts[1..].join("/"))
+    };
+
+    // Wine finds files whatever their case, so C:\PROGRAM FILES (X86) is as good as the real name
+    (lowercase(&dir) == lowercase(install_dir)).then(|| (exe.to_owned(), OsString::from(version)))
+}
+
+// the folder a Wine drive letter like "c:" stands for
+fn drive_root(drive: &str, prefix: Option<&Path>) -> Option<PathBuf> {
+    if let Some(root) = prefix.and_then(|p| fs::canonicalize(p.join("dosdevices").join(drive)).ok()) {
+        return Some(root)
+    }
+    match drive {
+        "c:" => prefix.map(|p| p.join("drive_c")),
+        "z:" => Some(PathBuf::from("/")),
+        _ => None,
+    }
+}
+
+fn lowercase(path: &Path) -> Vec<String> {
+    path.components()
+        .filter_map(|c| match c {
+            Component::Normal(n) => Some(n.to_string_lossy().to_lowercase()),
+            _ => None,
+        })
+        .collect()
+}
+
+fn nul_separated(bytes: &[u8]) -> impl Iterator<Item = String> + '_ {
+    bytes
+        .split(|b| *b == 0)
+        .filter(|s| !s.is_empty())
+        .map(|s| String::from_utf8_lossy(s).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn client(exe: &str, version: &str) -> Option<(String, OsString)> {
+        Some((exe.to_owned(), OsString::from(version)))
+    }
+
+    #[test]
+    fn native_path() {
+        let install = Path::new("/home/u/Games/World of Warcraft");
+        assert_eq!(client_in("/home/u/Games/World of Warcraft/_retail_/Wow.exe", install, None), client("Wow.exe", "_retail_"));
+        assert_eq!(client_in("/home/u/Other/World of Warcraft/_retail_/Wow.exe", install, None), None);
+        assert_eq!(client_in("/home/u/Games/World of Warcraft/_retail_/Battle.net.exe", install, None), None);
+    }
+
+    #[test]
+    fn wine_c_drive() {
+        let prefix = Path::new("/home/u/Games/battlenet");
+        let install = Path::new("/home/u/Games/battlenet/drive_c/Program Files (x86)/World of Warcraft");
+        let arg = r"C:\Program Files (x86)\World of Warcraft\_classic_era_\WowClassic.exe";
+        assert_eq!(client_in(arg, install, Some(prefix)), client("WowClassic.exe", "_classic_era_"));
+        // Wine doesn't care about case
+        assert_eq!(client_in(r"c:\PROGRAM FILES (X86)\world of warcraft\_ptr_\wowt.exe", install, Some(prefix)), client("wowt.exe", "_ptr_"));
+        // the same path in another prefix is another install
+        assert_eq!(client_in(arg, install, Some(Path::new("/home/u/.wine"))), None);
+        assert_eq!(client_in(arg, install, None), None);
+    }
+
+    #[test]
+    fn wine_z_drive() {
+        let install = Path::new("/mnt/games/World of Warcraft");
+        let arg = r"Z:\mnt\games\World of Warcraft\_retail_\Wow.exe";
+        assert_eq!(client_in(arg, install, None), client("Wow.exe", "_retail_"));
+        assert_eq!(client_in(arg, install, Some(Path::new("/nonexistent/prefix"))), client("Wow.exe", "_retail_"));
+        assert_eq!(client_in(r"Z:\mnt\games\World of Warcraft\_retail_\Wow.exe", Path::new("/games/World of Warcraft"), None), None);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn wine_other_drive() {
+        let prefix = std::env::temp_dir().join(format!("wow-profile-copy-ng-client-{}", std::process::id()));
+        let games = prefix.join("games");
+        fs::create_dir_all(prefix.join("dosdevices")).unwrap();
+        fs::create_dir_all(games.join("World of Warcraft")).unwrap();
+        std::os::unix::fs::symlink(&games, prefix.join("dosdevices").join("d:")).unwrap();
+
+        let install = fs::canonicalize(games.join("World of Warcraft")).unwrap();
+        let arg = r"D:\World of Warcraft\_retail_\Wow.exe";
+        let found = client_in(arg, &install, Some(&prefix));
+        // without the link, D: could be anywhere
+        let without = client_in(arg, &install, None);
+        fs::remove_dir_all(&prefix).unwrap();
+
+        assert_eq!(found, client("Wow.exe", "_retail_"));
+        assert_eq!(without, None);
+    }
+}
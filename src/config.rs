@@ -0,0 +1,307 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+// config-cache.wtf files, which hold the client's CVars one per line:
+//
+//   SET gxWindow "1"
+//   SET cameraDistanceMaxZoomFactor "2.6"
+//
+// CVar names aren't case sensitive, so lookups ignore case.
+
+use std::{collections::BTreeSet, fs, io::Error, path::Path};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConfigLine {
+    // `line` is the text it was read from, written back as it was until the value changes
+    Set { name: String, value: String, line: Option<String> },
+    // anything that isn't a SET, kept so the file can be written back as it was
+    Other(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct ConfigCache {
+    pub lines: Vec<ConfigLine>,
+    crlf: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Category {
+    Graphics,
+    Sound,
+    Camera,
+    Controls,
+    Nameplates,
+    Chat,
+    Interface,
+    Other,
+}
+
+// prefixes of CVar names in each category, lowercase. checked in order, so more specific ones go first.
+const CATEGORIES: [(Category, &[&str]); 7] = [
+    (Category::Graphics, &[
+        "gx", "graphics", "raidgraphics", "render", "ffx", "shadow", "sunshafts", "particle", "weather",
+        "projected", "ssao", "texture", "farclip", "horizon", "maxfps", "targetfps", "resample", "msaa",
+        "water", "liquid", "environment", "ground", "terrain", "physics", "lowlatency", "componenttexture",
+        "spelldensity", "refraction", "volumetric", "light", "outline", "sky", "depth", "vsync", "contrast",
+        "brightness", "gamma", "monitor", "windowresizelock",
+    ]),
+    (Category::Sound, &["sound", "voice", "ambience", "audio", "dialog", "music"]),
+    (Category::Camera, &["camera", "mouselook", "test_camera"]),
+    (Category::Nameplates, &["nameplate", "showenemy", "showfriendly"]),
+    (Category::Chat, &["chat", "whisper", "profanity", "showtimestamps", "combatlog", "removechatdelay", "wholly"]),
+    (Category::Controls, &[
+        "mouse", "autointeract", "interact", "autoloot", "softtarget", "deselect", "autoselfcast",
+        "actionbuttonusekeydown", "autodismount", "autoclearafk", "blocktrades", "blockchannelinvites",
+        "clicktomove", "enablemousespeed", "gamepad",
+    ]),
+    (Category::Interface, &[
+        "ui", "show", "floatingcombattext", "enablefloatingcombattext", "statustext", "raid", "compact",
+        "minimap", "buff", "tooltip", "scripterrors", "taint", "colorblind", "lockactionbars", "actionbar",
+        "watchframe", "questlog", "map", "unitname", "lastaddonversion", "addon", "timemgr", "tutorial",
+    ]),
+];
+
+// CVars that describe the machine rather than the player: resolution, window mode, render scale,
+// graphics quality and audio devices. copying them from a desktop to a Steam Deck breaks the target,
+// so by default the target keeps its own. entries ending in * match every CVar starting with the rest.
+pub const MACHINE_CVARS: [&str; 27] = [
+    "gx*", "graphics*", "raidGraphics*", "renderScale", "uiScale", "useUiScale",
+    "maxFPS", "maxFPSBk", "maxFPSLoading", "targetFPS", "useTargetFPS", "useMaxFPS", "useMaxFPSBk",
+    "MSAAQuality", "MSAAAlphaTest", "ffxAntiAliasingMode", "ResampleQuality", "ResampleSharpness",
+    "textureFilteringMode", "LowLatencyMode", "Gamma", "Contrast", "Brightness", "hwDetect",
+    "videoOptionsVersion", "Sound_OutputDriverIndex", "Sound_OutputDriverName",
+];
+
+impl std::fmt::Display for Category {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Category::Graphics => "Graphics",
+            Category::Sound => "Sound",
+            Category::Camera => "Camera",
+            Category::Controls => "Controls",
+            Category::Nameplates => "Nameplates",
+            Category::Chat => "Chat",
+            Category::Interface => "Interface",
+            Category::Other => "Other",
+        })
+    }
+}
+
+impl Category {
+    // a best guess from the CVar's name, there's no list of them on disk
+    pub fn of(name: &str) -> Category {
+        let name = name.to_ascii_lowercase();
+        CATEGORIES
+            .iter()
+            .find(|(_, prefixes)| prefixes.iter().any(|p| name.starts_with(p)))
+            .map(|(c, _)| *c)
+            .unwrap_or(Category::Other)
+    }
+}
+
+impl ConfigCache {
+    pub fn parse(input: &str) -> ConfigCache {
+        let lines = input
+            .lines()
+            .map(|line| match parse_set(line) {
+                Some((name, value)) => ConfigLine::Set { name, value, line: Some(line.to_owned()) },
+                None => ConfigLine::Other(line.to_owned()),
+            })
+            .collect();
+
+        ConfigCache { lines, crlf: input.contains("\r\n") }
+    }
+
+    pub fn read(path: &Path) -> Result<ConfigCache, Error> {
+        Ok(ConfigCache::parse(&String::from_utf8_lossy(&fs::read(path)?)))
+    }
+
+    // every CVar, in file order
+    pub fn cvars(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.lines.iter().filter_map(|l| match l {
+            ConfigLine::Set { name, value, .. } => Some((name.as_str(), value.as_str())),
+            ConfigLine::Other(_) => None,
+        })
+    }
+
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.cvars()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v)
+    }
+
+    // replaces the value of an existing CVar, or adds it to the end
+    pub fn set(&mut self, name: &str, value: &str) {
+        for line in self.lines.iter_mut() {
+            if let ConfigLine::Set { name: n, value: v, line: l } = line {
+                if n.eq_ignore_ascii_case(name) {
+                    if v != value {
+                        *v = value.to_owned();
+                        *l = None;
+                    }
+                    return
+                }
+            }
+        }
+        self.lines.push(ConfigLine::Set { name: name.to_owned(), value: value.to_owned(), line: None });
+    }
+
+    pub fn remove(&mut self, name: &str) {
+        self.lines.retain(|l| !matches!(l, ConfigLine::Set { name: n, .. } if n.eq_ignore_ascii_case(name)));
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let newline = if self.crlf {"\r\n"} else {"\n"};
+        let mut out = String::new();
+        for line in &self.lines {
+            match line {
+                ConfigLine::Set { line: Some(s), .. } => out += s,
+                ConfigLine::Set { name, value, line: None } => out += &format!("SET {} \"{}\"", name, value),
+                ConfigLine::Other(s) => out += s,
+            }
+            out += newline;
+        }
+        out.into_bytes()
+    }
+}
+
+// copies only the named CVars from src into dst, leaving everything else in dst as it is
+pub fn merge(dst: &ConfigCache, src: &ConfigCache, names: &BTreeSet<String>) -> ConfigCache {
+    let mut merged = dst.clone();
+    for (name, value) in src.cvars() {
+        if names.iter().any(|n| n.eq_ignore_ascii_case(name)) {
+            merged.set(name, value);
+        }
+    }
+    merged
+}
+
+// copies src over dst, except for CVars matching `keep`, which keep dst's values (or stay unset if
+// dst didn't have them). returns the new file, how many CVars kept dst's value, and how many of src's
+// were dropped because dst doesn't set them.
+pub fn replace_keeping(dst: &ConfigCache, src: &ConfigCache, keep: &BTreeSet<String>) -> (ConfigCache, usize, usize) {
+    let mut out = src.clone();
+    let mut kept = 0;
+    let mut dropped = 0;
+
+    out.lines.retain_mut(|l| match l {
+        ConfigLine::Set { name, value, line } if is_kept(keep, name) => match dst.get(name) {
+            Some(v) => {
+                kept += 1;
+                if value != v {
+                    *value = v.to_owned();
+                    *line = None;
+                }
+                true
+            },
+            None => {
+                dropped += 1;
+                false
+            },
+        },
+        _ => true,
+    });
+    for (name, value) in dst.cvars() {
+        if is_kept(keep, name) && out.get(name).is_none() {
+            kept += 1;
+            out.set(name, value);
+        }
+    }
+
+    (out, kept, dropped)
+}
+
+// whether a CVar matches any entry in a deny list like MACHINE_CVARS
+pub fn is_kept(keep: &BTreeSet<String>, name: &str) -> bool {
+    keep.iter().any(|pattern| match pattern.strip_suffix('*') {
+        Some(prefix) => name.get(..prefix.len()).is_some_and(|start| start.eq_ignore_ascii_case(prefix)),
+        None => pattern.eq_ignore_ascii_case(name),
+    })
+}
+
+// MACHINE_CVARS, as the deny list a copy starts with
+pub fn default_keep_cvars() -> BTreeSet<String> {
+    MACHINE_CVARS.iter().map(|c| c.to_string()).collect()
+}
+
+// `SET name "value"`, where the value runs to the last quote
+fn parse_set(line: &str) -> Option<(String, String)> {
+    let rest = line.strip_prefix("SET ")?;
+    let (name, value) = rest.split_once(' ')?;
+    let value = value.trim();
+    let value = value
+        .strip_prefix('"')
+        .and_then(|v| v.strip_suffix('"'))
+        .unwrap_or(value);
+    Some((name.to_owned(), value.to_owned()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DESKTOP: &str = "SET portal \"US\"\r\nSET gxWindow \"1\"\r\nSET gxMaximize \"1\"\r\nSET renderScale \"1\"\r\nSET cameraDistanceMaxZoomFactor \"2.6\"\r\nSET autoLootDefault \"1\"\r\n";
+    const DECK: &str = "SET portal \"US\"\r\nSET gxWindow \"0\"\r\nSET gxFullscreenResolution \"1280x800\"\r\nSET renderScale \"0.75\"\r\nSET cameraDistanceMaxZoomFactor \"1.9\"\r\n";
+
+    #[test]
+    fn parse_set_lines() {
+        assert_eq!(parse_set("SET gxWindow \"1\""), Some((String::from("gxWindow"), String::from("1"))));
+        // the value runs to the last quote, quotes inside it and all
+        assert_eq!(parse_set("SET lastAddonVersion \"say \"hi\"\""), Some((String::from("lastAddonVersion"), String::from("say \"hi\""))));
+        assert_eq!(parse_set("SET accounttype LT "), Some((String::from("accounttype"), String::from("LT"))));
+        assert_eq!(parse_set("SET emptyValue \"\""), Some((String::from("emptyValue"), String::new())));
+        assert_eq!(parse_set("SETgxWindow \"1\""), None);
+        assert_eq!(parse_set("SET lonely"), None);
+    }
+
+    #[test]
+    fn round_trip() {
+        let file = "SET portal \"US\"\n# a comment the client doesn't write\nSET gxWindow \"1\"\n";
+        assert_eq!(ConfigCache::parse(file).to_bytes(), file.as_bytes());
+        assert_eq!(ConfigCache::parse(DESKTOP).to_bytes(), DESKTOP.as_bytes());
+    }
+
+    #[test]
+    fn untouched_lines_stay_as_written() {
+        let mut config = ConfigCache::parse("SET accounttype LT\nSET spaced   \"1\"\nSET gxWindow \"1\"\n");
+        config.set("spaced", "1");
+        config.set("gxWindow", "0");
+        config.set("new", "x");
+        // only the lines whose value changed are written out again
+        assert_eq!(String::from_utf8(config.to_bytes()).unwrap(), "SET accounttype LT\nSET spaced   \"1\"\nSET gxWindow \"0\"\nSET new \"x\"\n");
+    }
+
+    #[test]
+    fn merge_only_named_cvars() {
+        let dst = ConfigCache::parse("SET gxWindow \"0\"\r\nSET renderScale \"0.75\"\r\n");
+        let names = BTreeSet::from([String::from("RENDERSCALE"), String::from("autoLootDefault"), String::from("notInSource")]);
+        let merged = merge(&dst, &ConfigCache::parse(DESKTOP), &names);
+        // names ignore case, and ones the source doesn't set are left alone
+        assert_eq!(String::from_utf8(merged.to_bytes()).unwrap(), "SET gxWindow \"0\"\r\nSET renderScale \"1\"\r\nSET autoLootDefault \"1\"\r\n");
+    }
+
+    #[test]
+    fn replace_keeping_machine_cvars() {
+        let (out, kept, dropped) = replace_keeping(&ConfigCache::parse(DECK), &ConfigCache::parse(DESKTOP), &default_keep_cvars());
+        // the desktop's gxMaximize is dropped since the deck never set it, and its gxFullscreenResolution is kept
+        assert_eq!(String::from_utf8(out.to_bytes()).unwrap(), "SET portal \"US\"\r\nSET gxWindow \"0\"\r\nSET renderScale \"0.75\"\r\nSET cameraDistanceMaxZoomFactor \"2.6\"\r\nSET autoLootDefault \"1\"\r\nSET gxFullscreenResolution \"1280x800\"\r\n");
+        assert_eq!((kept, dropped), (3, 1));
+
+        // nothing kept is a plain copy
+        let (out, kept, dropped) = replace_keeping(&ConfigCache::parse(DECK), &ConfigCache::parse(DESKTOP), &BTreeSet::new());
+        assert_eq!(out.to_bytes(), DESKTOP.as_bytes());
+        assert_eq!((kept, dropped), (0, 0));
+    }
+
+    #[test]
+    fn kept_ignores_case() {
+        let keep = BTreeSet::from([String::from("gx*"), String::from("renderScale")]);
+        assert!(is_kept(&keep, "GXWINDOW"));
+        assert!(is_kept(&keep, "renderscale"));
+        assert!(!is_kept(&keep, "renderScaleX"));
+        assert!(!is_kept(&keep, "g"));
+    }
+}
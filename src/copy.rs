@@ -0,0 +1,1742 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use crate::addons::{self, AddonStatus};
+use crate::backup;
+use crate::error;
+use crate::bindings::{self, BindingsCache};
+use crate::config::{self, ConfigCache};
+use crate::lua::SavedVariables;
+use crate::macros::{self, MacrosCache};
+use crate::profiles;
+use crate::wow::Wtf;
+use std::{collections::{BTreeMap, BTreeSet}, ffi::{OsStr, OsString}, fs, io::{Error, ErrorKind}, path::{Path, PathBuf}, sync::atomic::{AtomicBool, Ordering}, time::{SystemTime, UNIX_EPOCH}};
+
+const ACCOUNT_FILES: [&str; 4] = ["bindings-cache.wtf", "config-cache.wtf", "macros-cache.txt", "edit-mode-cache-account.txt"];
+const CHARACTER_FILES: [&str; 5] = ["AddOns.txt", "config-cache.wtf", "layout-local.txt", "macros-cache.txt", "edit-mode-cache-character.txt"];
+// key bindings are account-wide, unless a character has "Character Specific Key Bindings" turned on,
+// in which case it has its own copy of this file in its folder that's used instead
+const BINDINGS_FILE: &str = "bindings-cache.wtf";
+
+// a copy from one character to another, in the same install or another one.
+// versions are given by their folder name, e.g. "_retail_"
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CopyRequest {
+    pub src_install_dir: PathBuf,
+    pub src_ver: OsString,
+    pub src_wtf: Wtf,
+    // the install being copied to, the same as the source's unless copying between installs
+    pub dst_install_dir: PathBuf,
+    pub dst_ver: OsString,
+    pub dst_wtf: Wtf,
+    pub overwrite_account: bool,
+    pub skip_savedvars: BTreeSet<SavedVariablesFile>,
+    // CVars to merge into config-cache.wtf at each scope. scopes without an entry get the whole file.
+    pub merge_cvars: BTreeMap<Scope, BTreeSet<String>>,
+    // CVars that are never copied, the target keeps its own. see config::MACHINE_CVARS.
+    pub keep_cvars: BTreeSet<String>,
+    // keys whose bindings are merged into bindings-cache.wtf, or None to copy the whole file
+    pub merge_bindings: Option<BTreeSet<String>>,
+    // macros merged by name into macros-cache.txt at each scope. scopes without an entry get the whole file.
+    pub merge_macros: BTreeMap<Scope, BTreeSet<String>>,
+    // addon folders copied from the source version's Interface/AddOns to the destination's
+    pub sync_addons: BTreeSet<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Scope {
+    Account,
+    Character,
+}
+
+// one addon's SavedVariables file, e.g. WeakAuras.lua in the account SavedVariables folder
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct SavedVariablesFile {
+    pub scope: Scope,
+    pub file_name: OsString,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    Copy,
+    Overwrite,
+    CreateDir,
+    Delete,
+    Rewrite,
+    // a whole folder, for addons. files already in the destination that the source doesn't have are left alone.
+    CopyDir,
+}
+
+// AceDB profileKeys to add for the destination character, pointing at the source character's profile
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyRewrite {
+    pub from: String,
+    pub to: String,
+}
+
+// a change made to a file on its way to the destination
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Transform {
+    ProfileKeys(KeyRewrite),
+    // only these CVars are taken from the source, the rest of the destination's config-cache.wtf stays as it is
+    MergeCvars(BTreeSet<String>),
+    // the whole file is copied apart from these CVars, which keep the destination's values
+    KeepCvars(BTreeSet<String>),
+    // only these keys' bindings are taken from the source
+    MergeBindings(BTreeSet<String>),
+    // only these macros are taken from the source, as many as fit in the destination's slots for the scope
+    MergeMacros(Scope, usize, BTreeSet<String>),
+}
+
+// one step of a copy. `src` is only set for copies and overwrites.
+// `transform` is applied to the copied file, or to `dst` in place for Action::Rewrite.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlanEntry {
+    pub action: Action,
+    pub src: Option<PathBuf>,
+    pub dst: PathBuf,
+    pub size: Option<u64>,
+    pub src_modified: Option<SystemTime>,
+    pub dst_modified: Option<SystemTime>,
+    pub transform: Option<Transform>,
+}
+
+// everything a copy is going to do, worked out before anything is touched
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CopyPlan {
+    pub dst_version: PathBuf,
+    pub dst_root: PathBuf,
+    pub dst_wtf: Wtf,
+    pub entries: Vec<PlanEntry>,
+    pub notes: Vec<String>,
+}
+
+// how far a copy has got, reported just before `file` is staged.
+// counts are plan entries, so an addon folder is one however many files it has.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Progress {
+    pub done: usize,
+    pub total: usize,
+    pub file: PathBuf,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+    Done,
+    // tried and failed, or never tried because the copy couldn't start
+    Failed,
+    // not made, because the copy was cancelled or something else failed
+    Skipped,
+}
+
+// what happened to one entry of a plan
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReportEntry {
+    pub action: Action,
+    pub src: Option<PathBuf>,
+    pub dst: PathBuf,
+    // how much was written, if anything was
+    pub bytes: Option<u64>,
+    pub status: Status,
+    // what a transform changed, or how many files were in a folder
+    pub note: Option<String>,
+    pub error: Option<String>,
+}
+
+// what carrying out one plan did, entry by entry, in the same order as the plan
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CopyReport {
+    pub header: String,
+    pub dst_version: PathBuf,
+    pub dst_root: PathBuf,
+    // None if the copy couldn't get as far as taking one
+    pub snapshot: Option<PathBuf>,
+    pub notes: Vec<String>,
+    pub entries: Vec<ReportEntry>,
+    // why none of it was done, if it couldn't start or couldn't be undone, and any cleanup that failed
+    pub error: Option<String>,
+    pub cancelled: bool,
+    // something failed, so none of the changes were kept
+    pub rolled_back: bool,
+}
+
+// how many entries of one or more reports ended up in each status
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Counts {
+    pub done: usize,
+    pub failed: usize,
+    pub skipped: usize,
+    // changes that were made for a character something else failed for, and couldn't be undone
+    pub not_undone: usize,
+}
+
+impl std::fmt::Display for Action {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Action::Copy => "copy",
+            Action::Overwrite => "overwrite",
+            Action::CreateDir => "create",
+            Action::Delete => "delete",
+            Action::Rewrite => "rewrite",
+            Action::CopyDir => "copy dir",
+        })
+    }
+}
+
+impl std::fmt::Display for Status {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Status::Done => "done",
+            Status::Failed => "failed",
+            Status::Skipped => "skipped",
+        })
+    }
+}
+
+// the whole entry with full paths, for exported reports
+impl std::fmt::Display for ReportEntry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:<7} {:<9} {:?}", self.status.to_string(), self.action.to_string(), self.dst.as_os_str())?;
+        if let Some(src) = &self.src {
+            write!(f, " from {:?}", src.as_os_str())?;
+        }
+        if let Some(bytes) = self.bytes {
+            write!(f, " ({})", format_size(bytes))?;
+        }
+        if let Some(note) = &self.note {
+            write!(f, ", {}", note)?;
+        }
+        if let Some(error) = &self.error {
+            write!(f, ": {}", error)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::fmt::Display for Counts {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let outcome = if self.failed > 0 && self.done == 0 {
+            "copy failed"
+        } else if self.failed > 0 {
+            "copy partly failed"
+        } else if self.skipped > 0 {
+            "copy cancelled"
+        } else {
+            "copy finished"
+        };
+        write!(f, "{}: {} changes made, {} failed, {} skipped", outcome, self.done, self.failed, self.skipped)?;
+        if self.not_undone > 0 {
+            write!(f, ", {} couldn't be undone", self.not_undone)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::fmt::Display for Scope {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Scope::Account => "account",
+            Scope::Character => "character",
+        })
+    }
+}
+
+impl SavedVariablesFile {
+    // the addon's name, which is the file name without .lua
+    pub fn addon(&self) -> String {
+        Path::new(&self.file_name)
+            .file_stem()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .into_owned()
+    }
+}
+
+impl std::fmt::Display for SavedVariablesFile {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} ({})", self.addon(), self.scope)
+    }
+}
+
+impl CopyRequest {
+    pub fn new(install_dir: impl Into<PathBuf>, src_ver: impl Into<OsString>, src_wtf: Wtf, dst_ver: impl Into<OsString>, dst_wtf: Wtf) -> Self {
+        let install_dir: PathBuf = install_dir.into();
+        CopyRequest {
+            src_install_dir: install_dir.clone(),
+            src_ver: src_ver.into(),
+            src_wtf,
+            dst_install_dir: install_dir,
+            dst_ver: dst_ver.into(),
+            dst_wtf,
+            overwrite_account: true,
+            skip_savedvars: BTreeSet::new(),
+            merge_cvars: BTreeMap::new(),
+            keep_cvars: config::default_keep_cvars(),
+            merge_bindings: None,
+            merge_macros: BTreeMap::new(),
+            sync_addons: BTreeSet::new(),
+        }
+    }
+
+    // {install}/{version}/WTF/Account/{account}
+    pub fn src_root(&self) -> PathBuf {
+        account_root(&self.src_install_dir, &self.src_ver, &self.src_wtf)
+    }
+
+    pub fn dst_root(&self) -> PathBuf {
+        account_root(&self.dst_install_dir, &self.dst_ver, &self.dst_wtf)
+    }
+
+    // {install}/{version}
+    pub fn src_version(&self) -> PathBuf {
+        self.src_install_dir.join(&self.src_ver)
+    }
+
+    pub fn dst_version(&self) -> PathBuf {
+        self.dst_install_dir.join(&self.dst_ver)
+    }
+
+    fn skips(&self, scope: Scope, file_name: &OsStr) -> bool {
+        self.skip_savedvars.contains(&SavedVariablesFile { scope, file_name: file_name.to_os_string() })
+    }
+
+    // how one of the client configuration files is changed on its way over, if at all
+    fn transform_for(&self, scope: Scope, file: &str) -> Option<Transform> {
+        if file == BINDINGS_FILE {
+            return self.merge_bindings.clone().map(Transform::MergeBindings)
+        }
+        if file == "macros-cache.txt" {
+            let slots = macro_slots(scope, addons::Flavor::of(&self.dst_ver));
+            return self.merge_macros.get(&scope).map(|names| Transform::MergeMacros(scope, slots, names.clone()))
+        }
+        if file != "config-cache.wtf" {
+            return None
+        }
+        match self.merge_cvars.get(&scope) {
+            Some(names) => Some(Transform::MergeCvars(
+                names.iter().filter(|n| !config::is_kept(&self.keep_cvars, n)).cloned().collect()
+            )),
+            None if !self.keep_cvars.is_empty() => Some(Transform::KeepCvars(self.keep_cvars.clone())),
+            None => None,
+        }
+    }
+}
+
+impl PlanEntry {
+    // plans copying src over dst, as an overwrite if dst is already there
+    pub fn copy(src: PathBuf, dst: PathBuf) -> Result<PlanEntry, Error> {
+        let src_meta = fs::metadata(&src)?;
+        let dst_meta = match dst.try_exists()? {
+            true => Some(fs::metadata(&dst)?),
+            false => None,
+        };
+
+        Ok(PlanEntry {
+            action: if dst_meta.is_some() {Action::Overwrite} else {Action::Copy},
+            src: Some(src),
+            dst,
+            size: Some(src_meta.len()),
+            src_modified: src_meta.modified().ok(),
+            dst_modified: dst_meta.and_then(|m| m.modified().ok()),
+            transform: None,
+        })
+    }
+
+    pub fn delete(dst: PathBuf) -> Result<PlanEntry, Error> {
+        let dst_meta = fs::metadata(&dst)?;
+
+        Ok(PlanEntry {
+            action: Action::Delete,
+            src: None,
+            dst,
+            size: Some(dst_meta.len()),
+            src_modified: None,
+            dst_modified: dst_meta.modified().ok(),
+            transform: None,
+        })
+    }
+
+    // plans changing dst's profile keys without copying anything over it.
+    // dst may not exist yet if an earlier copy in a batch is going to create it.
+    pub fn rewrite(dst: PathBuf, rewrite: KeyRewrite) -> Result<PlanEntry, Error> {
+        let dst_meta = match dst.try_exists()? {
+            true => Some(fs::metadata(&dst)?),
+            false => None,
+        };
+
+        Ok(PlanEntry {
+            action: Action::Rewrite,
+            src: None,
+            dst,
+            size: dst_meta.as_ref().map(|m| m.len()),
+            src_modified: None,
+            dst_modified: dst_meta.and_then(|m| m.modified().ok()),
+            transform: Some(Transform::ProfileKeys(rewrite)),
+        })
+    }
+
+    // plans copying a folder and everything in it
+    pub fn copy_dir(src: PathBuf, dst: PathBuf) -> Result<PlanEntry, Error> {
+        let mut size = 0;
+        for file in dir_files(&src)? {
+            size += fs::metadata(src.join(file))?.len();
+        }
+
+        Ok(PlanEntry {
+            action: Action::CopyDir,
+            size: Some(size),
+            src_modified: None,
+            dst_modified: None,
+            src: Some(src),
+            dst,
+            transform: None,
+        })
+    }
+
+    pub fn create_dir(dst: PathBuf) -> PlanEntry {
+        PlanEntry {
+            action: Action::CreateDir,
+            src: None,
+            dst,
+            size: None,
+            src_modified: None,
+            dst_modified: None,
+            transform: None,
+        }
+    }
+}
+
+impl PlanEntry {
+    // what dst will contain once this entry is carried out, for copies and rewrites
+    pub fn contents(&self) -> Result<Option<Vec<u8>>, Error> {
+        let path = match (self.action, &self.src) {
+            (Action::Copy | Action::Overwrite, Some(src)) => src,
+            (Action::Rewrite, _) => &self.dst,
+            _ => return Ok(None),
+        };
+
+        let contents = fs::read(path)?;
+        Ok(Some(match &self.transform {
+            Some(t) => t.apply(contents, &self.dst)?.0,
+            None => contents,
+        }))
+    }
+}
+
+impl Transform {
+    // returns the transformed file, and a note for the log if anything changed.
+    // merges read `dst`, so this needs to happen right before it's written.
+    fn apply(&self, contents: Vec<u8>, dst: &Path) -> Result<(Vec<u8>, Option<String>), Error> {
+        match self {
+            Transform::ProfileKeys(r) => Ok(match apply_rewrite(contents, r) {
+                (contents, 0) => (contents, None),
+                (contents, n) => (contents, Some(format!("pointed {} profile keys at {}'s profile", n, r.from))),
+            }),
+            Transform::MergeCvars(names) => {
+                let src = ConfigCache::parse(&String::from_utf8_lossy(&contents));
+                let dst = or_empty(ConfigCache::read(dst))?;
+                let merged = src.cvars().filter(|(n, _)| names.iter().any(|m| m.eq_ignore_ascii_case(n))).count();
+                Ok((config::merge(&dst, &src, names).to_bytes(), Some(format!("merged {} CVars", merged))))
+            },
+            Transform::KeepCvars(keep) => {
+                let src = ConfigCache::parse(&String::from_utf8_lossy(&contents));
+                let dst = or_empty(ConfigCache::read(dst))?;
+                Ok(match config::replace_keeping(&dst, &src, keep) {
+                    (_, 0, 0) => (contents, None),
+                    (out, kept, dropped) => {
+                        let mut notes: Vec<String> = vec![];
+                        if kept > 0 {
+                            notes.push(format!("kept the target's value for {} machine-specific CVars", kept));
+                        }
+                        if dropped > 0 {
+                            notes.push(format!("dropped {} machine-specific CVars the target doesn't set", dropped));
+                        }
+                        (out.to_bytes(), Some(notes.join(", ")))
+                    },
+                })
+            },
+            Transform::MergeBindings(keys) => {
+                let src = BindingsCache::parse(&String::from_utf8_lossy(&contents));
+                let dst = or_empty(BindingsCache::read(dst))?;
+                let (merged, replaced) = bindings::merge(&dst, &src, keys);
+                let mut note = format!("merged {} bindings", keys.iter().filter(|k| src.get(k).is_some()).count());
+                if !replaced.is_empty() {
+                    note += &format!(", replacing {}", replaced
+                        .iter()
+                        .map(|c| format!("{} ({} -> {})", c.key, c.dst, c.src))
+                        .collect::<Vec<_>>()
+                        .join(", "));
+                }
+                Ok((merged.to_bytes(), Some(note)))
+            },
+            Transform::MergeMacros(_, slots, names) => {
+                let src = MacrosCache::parse(&String::from_utf8_lossy(&contents))?;
+                let dst = or_empty(MacrosCache::read(dst))?;
+                let (merged, report) = macros::merge(&dst, &src, names, *slots);
+                let mut note = format!("replaced {} macros and added {}", report.replaced.len(), report.added.len());
+                if !report.overflow.is_empty() {
+                    note += &format!(", no free slots for {}", report.overflow.join(", "));
+                }
+                Ok((merged.to_bytes(), Some(note)))
+            },
+        }
+    }
+}
+
+impl CopyPlan {
+    // names the destination, for separating plans in a batch
+    pub fn header(&self) -> String {
+        format!("== {} ({}, {}) ==",
+            self.dst_wtf,
+            self.dst_wtf.account.to_string_lossy(),
+            self.dst_version.file_name().unwrap_or_default().to_string_lossy()
+        )
+    }
+
+    // human readable summary of the plan, one line per entry
+    pub fn lines(&self) -> Vec<String> {
+        let mut lines: Vec<String> = self.notes.clone();
+        lines.extend(self.entries.iter().map(|e| self.entry_line(e)));
+        lines
+    }
+
+    // one entry of the plan, with paths relative to the destination account folder,
+    // or the version folder for things outside of WTF
+    pub fn entry_line(&self, e: &PlanEntry) -> String {
+        let name = e.dst.strip_prefix(&self.dst_root)
+            .or_else(|_| e.dst.strip_prefix(&self.dst_version))
+            .unwrap_or(&e.dst);
+        let mut line = format!("{:<9} {}", e.action.to_string(), name.to_string_lossy());
+        if let Some(size) = e.size {
+            line += &format!(" ({})", format_size(size));
+        }
+        if let Some(t) = e.src_modified {
+            line += &format!(", source modified {}", format_time(t));
+        }
+        if let Some(t) = e.dst_modified {
+            line += &format!(", destination modified {}", format_time(t));
+        }
+        match &e.transform {
+            Some(Transform::ProfileKeys(r)) => line += &format!(", profile keys for {} use {}'s profile", r.to, r.from),
+            Some(Transform::MergeCvars(names)) => line += &format!(", merging {} selected CVars", names.len()),
+            Some(Transform::KeepCvars(_)) => line += ", keeping the target's machine-specific CVars",
+            Some(Transform::MergeBindings(keys)) => line += &format!(", merging {} selected key bindings", keys.len()),
+            Some(Transform::MergeMacros(_, _, names)) => line += &format!(", merging {} selected macros", names.len()),
+            None => (),
+        }
+        line
+    }
+}
+
+impl CopyReport {
+    // a report for a plan that didn't get carried out, with every entry in `status`
+    fn unfinished(plan: &CopyPlan, status: Status) -> CopyReport {
+        CopyReport {
+            header: plan.header(),
+            dst_version: plan.dst_version.clone(),
+            dst_root: plan.dst_root.clone(),
+            snapshot: None,
+            notes: plan.notes.clone(),
+            entries: plan.entries.iter().map(|e| ReportEntry {
+                action: e.action,
+                src: e.src.clone(),
+                dst: e.dst.clone(),
+                bytes: None,
+                status,
+                note: None,
+                error: None,
+            }).collect(),
+            error: None,
+            cancelled: false,
+            rolled_back: false,
+        }
+    }
+
+    pub fn counts(&self) -> Counts {
+        Counts::of(std::slice::from_ref(self))
+    }
+
+    // adds to the error, after whatever is there already
+    fn add_error(&mut self, error: String) {
+        self.error = Some(match self.error.take() {
+            Some(e) => format!("{}; {}", e, error),
+            None => error,
+        });
+    }
+
+    // human readable report, one line per entry, like CopyPlan::lines
+    pub fn lines(&self) -> Vec<String> {
+        let mut lines: Vec<String> = vec![];
+        if let Some(snapshot) = &self.snapshot {
+            lines.push(format!("saved snapshot: {:?}", snapshot.file_name().unwrap_or_default()));
+        }
+        if let Some(error) = &self.error {
+            lines.push(format!("error: {}", error));
+        }
+        lines.extend(self.notes.iter().cloned());
+        lines.extend(self.entries.iter().map(|e| self.entry_line(e)));
+        if self.cancelled {
+            lines.push(String::from("cancelled, nothing was changed"));
+        }
+        if self.rolled_back {
+            lines.push(String::from("something failed, so every change was undone and nothing was changed"));
+        }
+        lines
+    }
+
+    // one entry, with its path relative to the destination account or version folder like CopyPlan::entry_line
+    pub fn entry_line(&self, e: &ReportEntry) -> String {
+        let name = e.dst.strip_prefix(&self.dst_root)
+            .or_else(|_| e.dst.strip_prefix(&self.dst_version))
+            .unwrap_or(&e.dst);
+        let mut line = format!("{:<7} {:<9} {}", e.status.to_string(), e.action.to_string(), name.to_string_lossy());
+        if let Some(bytes) = e.bytes {
+            line += &format!(" ({})", format_size(bytes));
+        }
+        if let Some(note) = &e.note {
+            line += &format!(", {}", note);
+        }
+        if let Some(error) = &e.error {
+            line += &format!(": {}", error);
+        }
+        line
+    }
+}
+
+impl Counts {
+    pub fn of(reports: &[CopyReport]) -> Counts {
+        let mut counts = Counts::default();
+        for report in reports {
+            let failed = report.entries.iter().any(|e| e.status == Status::Failed);
+            for e in &report.entries {
+                match e.status {
+                    Status::Done if failed => {
+                        counts.done += 1;
+                        counts.not_undone += 1;
+                    },
+                    Status::Done => counts.done += 1,
+                    Status::Failed => counts.failed += 1,
+                    Status::Skipped => counts.skipped += 1,
+                }
+            }
+        }
+        counts
+    }
+}
+
+// the reports as a text file: the counts, then every entry with its full paths
+pub fn report_text(reports: &[CopyReport]) -> String {
+    let mut out = format!("{}\n", Counts::of(reports));
+    for report in reports {
+        out += &format!("\n{}\n", report.header);
+        if let Some(snapshot) = &report.snapshot {
+            out += &format!("saved snapshot: {:?}\n", snapshot.as_os_str());
+        }
+        if let Some(error) = &report.error {
+            out += &format!("error: {}\n", error);
+        }
+        for note in &report.notes {
+            out += &format!("{}\n", note);
+        }
+        for e in &report.entries {
+            out += &format!("{}\n", e);
+        }
+    }
+    out
+}
+
+// works out what copying config files and savedvariables from one character to another would do
+pub fn plan_copy(req: &CopyRequest) -> Result<CopyPlan, error::Error> {
+    Ok(plan_copy_with(req, false, false)?)
+}
+
+// plans copies to several characters. account-level files are only copied once per
+// destination account, later characters on the same account just get their profile keys.
+// addon folders are only copied once per destination version.
+pub fn plan_batch(reqs: &[CopyRequest]) -> Result<Vec<CopyPlan>, error::Error> {
+    let mut copied_accounts: Vec<PathBuf> = vec![];
+    let mut synced_versions: Vec<PathBuf> = vec![];
+    let mut plans: Vec<CopyPlan> = vec![];
+
+    for req in reqs {
+        let dst_root = req.dst_root();
+        let dst_version = req.dst_version();
+        plans.push(plan_copy_with(req, copied_accounts.contains(&dst_root), synced_versions.contains(&dst_version))?);
+        if req.overwrite_account && req.src_root() != dst_root {
+            copied_accounts.push(dst_root);
+        }
+        if !req.sync_addons.is_empty() {
+            synced_versions.push(dst_version);
+        }
+    }
+
+    Ok(plans)
+}
+
+// `account_copied` is set when an earlier copy in a batch already copied the destination's account files,
+// `addons_synced` when it already copied the addon folders to the destination version
+fn plan_copy_with(req: &CopyRequest, account_copied: bool, addons_synced: bool) -> Result<CopyPlan, Error> {
+    let dst_version = req.dst_version();
+    let src_root = req.src_root();
+    let dst_root = req.dst_root();
+
+    let mut plan = CopyPlan {
+        dst_version,
+        dst_root: dst_root.clone(),
+        dst_wtf: req.dst_wtf.clone(),
+        entries: vec![],
+        notes: vec![],
+    };
+
+    // {install}/WTF/Account/{account}/{realm}/{character}
+    let src_character = src_root
+    .join(&req.src_wtf.realm)
+    .join(&req.src_wtf.character);
+
+    let dst_character = dst_root
+    .join(&req.dst_wtf.realm)
+    .join(&req.dst_wtf.character);
+
+    let src_character_bindings = src_character.join(BINDINGS_FILE).try_exists()?;
+    let dst_character_bindings = dst_character.join(BINDINGS_FILE).try_exists()?;
+
+    let src_key = profiles::character_key(&req.src_wtf);
+    let dst_key = profiles::character_key(&req.dst_wtf);
+    let rewrite = if src_key != dst_key {
+        Some(KeyRewrite { from: src_key, to: dst_key })
+    } else {
+        None
+    };
+
+    // account saved variables
+    // {install_dir}/WTF/Account/{account number}/SavedVariables
+    let src_savedvars = src_root.join("SavedVariables");
+    let dst_savedvars = dst_root.join("SavedVariables");
+
+    // the same account folder in the same version is the same set of files, there's nothing to copy
+    if src_root == dst_root || !req.overwrite_account || account_copied {
+        plan.notes.push(String::from("skipping account copy."));
+
+        // ...but the destination character still needs to be pointed at the source's profiles,
+        // in files that are shared with the source or were copied from it earlier in a batch
+        let shared = src_root == dst_root || account_copied;
+        if let Some(rewrite) = rewrite.as_ref().filter(|_| shared && src_savedvars.is_dir()) {
+            for file in lua_files(&src_savedvars)? {
+                if req.skips(Scope::Account, &file) {
+                    continue
+                }
+                if profiles::might_have_key(&fs::read(src_savedvars.join(&file))?, &rewrite.from) {
+                    plan.entries.push(PlanEntry::rewrite(dst_savedvars.join(&file), rewrite.clone())?);
+                }
+            }
+        }
+    } else {
+        // client configuration
+        for file in ACCOUNT_FILES {
+            // bindings picked from a source with its own set are merged into the character's file
+            // further down, the account's are left alone
+            if file == BINDINGS_FILE && src_character_bindings && req.merge_bindings.is_some() {
+                plan.notes.push(String::from("skipping account key bindings, merging the source character's own."));
+                continue
+            }
+            let transform = req.transform_for(Scope::Account, file);
+            note_merge_problems(&mut plan, &src_root.join(file), &dst_root.join(file), transform.as_ref())?;
+            plan_file(&mut plan, src_root.join(file), dst_root.join(file), transform)?;
+        }
+
+        for file in lua_files(&src_savedvars)? {
+            if req.skips(Scope::Account, &file) {
+                plan.notes.push(format!("skipping {}, not selected", SavedVariablesFile { scope: Scope::Account, file_name: file }));
+                continue
+            }
+            let src = src_savedvars.join(&file);
+            let mut entry = PlanEntry::copy(src.clone(), dst_savedvars.join(&file))?;
+            entry.transform = match &rewrite {
+                Some(r) if profiles::might_have_key(&fs::read(&src)?, &r.from) => Some(Transform::ProfileKeys(r.clone())),
+                _ => None,
+            };
+            plan.entries.push(entry);
+        }
+
+        plan_delete(&mut plan, dst_root.join("cache.md5"))?;
+    }
+
+    // character configuration
+    for file in CHARACTER_FILES {
+        let transform = req.transform_for(Scope::Character, file);
+        note_merge_problems(&mut plan, &src_character.join(file), &dst_character.join(file), transform.as_ref())?;
+        plan_file(&mut plan, src_character.join(file), dst_character.join(file), transform)?;
+    }
+
+    // addon folders, which the character's AddOns.txt is checked against below
+    let src_version = req.src_version();
+    if src_version != plan.dst_version && !addons_synced {
+        let flavor = addons::Flavor::of(&req.dst_ver);
+        for name in &req.sync_addons {
+            let src = addons::addons_dir(&src_version).join(name);
+            if !src.is_dir() {
+                plan.notes.push(format!("source missing, skipping: {:?}", src.as_os_str()));
+                continue
+            }
+            let interface = addons::interfaces(&src_version, name)?;
+            if !interface.iter().any(|i| flavor.supports(*i)) {
+                plan.notes.push(format!("warning: {} doesn't say it supports {} (## Interface: {})",
+                    name, flavor, interface.iter().map(u32::to_string).collect::<Vec<_>>().join(", ")));
+            }
+            plan.entries.push(PlanEntry::copy_dir(src, addons::addons_dir(&plan.dst_version).join(name))?);
+        }
+    } else if src_version == plan.dst_version && !req.sync_addons.is_empty() {
+        plan.notes.push(String::from("skipping addon folders, the source and target are the same version."));
+    }
+
+    // addons the character has turned on that the destination version doesn't have, or has an older version of.
+    // within one version both characters share the same addons, so there's nothing to check.
+    let addons_txt = src_character.join("AddOns.txt");
+    if src_version != plan.dst_version && addons_txt.try_exists()? {
+        for check in addons::check_addons(&addons_txt, &src_version, &plan.dst_version)? {
+            // the folders being copied will be there, and up to date
+            if check.enabled && check.status != AddonStatus::Installed && !req.sync_addons.contains(&check.name) {
+                plan.notes.push(format!("warning: {}", check));
+            }
+        }
+    }
+
+    // the destination gets the bindings the source character actually uses. if either side has its own
+    // set they go in the destination's character folder, so they take effect without touching the account's.
+    let src_bindings = match (src_character_bindings, dst_character_bindings) {
+        (true, false) => {
+            plan.notes.push(String::from("the source uses character specific key bindings, the target will too."));
+            Some(src_character.join(BINDINGS_FILE))
+        },
+        (true, true) => Some(src_character.join(BINDINGS_FILE)),
+        (false, true) => {
+            plan.notes.push(String::from("the target uses character specific key bindings, giving it the source's account bindings."));
+            Some(src_root.join(BINDINGS_FILE))
+        },
+        (false, false) => None,
+    };
+    if let Some(src) = src_bindings {
+        let dst = dst_character.join(BINDINGS_FILE);
+        let transform = req.transform_for(Scope::Character, BINDINGS_FILE);
+        note_merge_problems(&mut plan, &src, &dst, transform.as_ref())?;
+        plan_file(&mut plan, src, dst, transform)?;
+    }
+
+    // character saved variables
+    let src_savedvars = src_character.join("SavedVariables");
+    let dst_savedvars = dst_character.join("SavedVariables");
+
+    if !dst_savedvars.try_exists()? {
+        plan.entries.push(PlanEntry::create_dir(dst_savedvars.clone()));
+    }
+
+    for file in lua_files(&src_savedvars)? {
+        if req.skips(Scope::Character, &file) {
+            plan.notes.push(format!("skipping {}, not selected", SavedVariablesFile { scope: Scope::Character, file_name: file }));
+            continue
+        }
+        plan_file(&mut plan, src_savedvars.join(&file), dst_savedvars.join(&file), None)?;
+    }
+
+    plan_delete(&mut plan, dst_character.join("cache.md5"))?;
+
+    Ok(plan)
+}
+
+// plans and carries out a copy in one go
+pub fn do_copy(req: &CopyRequest) -> Result<CopyReport, error::Error> {
+    execute_plan(&plan_copy(req)?)
+}
+
+// carries out each plan in turn, with a report for each destination.
+// each destination is all or nothing, and a failure for one doesn't stop the others.
+pub fn execute_batch(plans: &[CopyPlan]) -> Vec<CopyReport> {
+    execute_batch_with(plans, &AtomicBool::new(false), |_| ())
+}
+
+// execute_batch, reporting progress across every plan, and stopping once `cancel` is set. plans that were
+// already done stay done.
+pub fn execute_batch_with(plans: &[CopyPlan], cancel: &AtomicBool, mut progress: impl FnMut(Progress)) -> Vec<CopyReport> {
+    let mut reports: Vec<CopyReport> = vec![];
+    let total = plans.iter().map(|p| p.entries.len()).sum();
+    let mut done = 0;
+
+    for plan in plans {
+        if cancel.load(Ordering::Relaxed) {
+            let mut report = CopyReport::unfinished(plan, Status::Skipped);
+            report.cancelled = true;
+            reports.push(report);
+            continue
+        }
+        reports.push(match run_plan(plan, cancel, done, total, &mut progress) {
+            Ok(report) => report,
+            Err(e) => {
+                let mut report = CopyReport::unfinished(plan, Status::Failed);
+                report.error = Some(e.to_string());
+                report
+            },
+        });
+        done += plan.entries.len();
+    }
+
+    reports
+}
+
+// snapshots everything the plan is about to overwrite or delete, then carries it out. it's all or nothing:
+// every change is staged in temporary files first and only swapped in once they're all ready, and if
+// anything fails the changes already made are undone. errors if it couldn't start, otherwise failures
+// are in the report.
+pub fn execute_plan(plan: &CopyPlan) -> Result<CopyReport, error::Error> {
+    execute_plan_with(plan, &AtomicBool::new(false), |_| ())
+}
+
+// execute_plan, reporting progress as each file is staged, and stopping before the changes are swapped in
+// once `cancel` is set
+pub fn execute_plan_with(plan: &CopyPlan, cancel: &AtomicBool, mut progress: impl FnMut(Progress)) -> Result<CopyReport, error::Error> {
+    Ok(run_plan(plan, cancel, 0, plan.entries.len(), &mut progress)?)
+}
+
+// `start` is how many files earlier plans in the batch had, out of `total`
+fn run_plan(plan: &CopyPlan, cancel: &AtomicBool, start: usize, total: usize, progress: &mut dyn FnMut(Progress)) -> Result<CopyReport, Error> {
+    let mut targets: Vec<PathBuf> = vec![];
+    for e in &plan.entries {
+        match (e.action, &e.src) {
+            (Action::CreateDir, _) => (),
+            (Action::CopyDir, Some(src)) => targets.extend(dir_files(src)?.into_iter().map(|f| e.dst.join(f))),
+            _ => targets.push(e.dst.clone()),
+        }
+    }
+
+    let snapshot = backup::take_snapshot(&plan.dst_version, &plan.dst_wtf, &targets)?;
+    let mut report = CopyReport::unfinished(plan, Status::Skipped);
+    let mut staged = Staged::default();
+    // how much each entry writes and anything worth noting about it, for the report once it's committed
+    let mut outcomes: Vec<(Option<u64>, Option<String>)> = vec![];
+
+    // everything is written to temporary files first, so stopping here leaves the destination as it was
+    for (i, e) in plan.entries.iter().enumerate() {
+        if cancel.load(Ordering::Relaxed) {
+            report.cancelled = true;
+            break
+        }
+        progress(Progress { done: start + i, total, file: e.dst.clone() });
+
+        match staged.stage(i, e) {
+            Ok(outcome) => outcomes.push(outcome),
+            Err(err) => {
+                report.entries[i].status = Status::Failed;
+                report.entries[i].error = Some(err.to_string());
+                report.rolled_back = true;
+                break
+            },
+        }
+    }
+
+    if report.cancelled || report.rolled_back {
+        if let Err(e) = staged.discard() {
+            report.add_error(format!("couldn't clean up the temporary files: {}", e));
+        }
+        // nothing was changed, so there's nothing to restore
+        if let Err(e) = backup::delete_snapshot(&snapshot) {
+            report.add_error(format!("couldn't remove the unneeded snapshot: {}", e));
+        }
+        return Ok(report)
+    }
+    match staged.commit() {
+        Ok(()) => {
+            report.snapshot = Some(snapshot.dir);
+            for (entry, (bytes, note)) in report.entries.iter_mut().zip(outcomes) {
+                entry.status = Status::Done;
+                entry.bytes = bytes;
+                entry.note = note;
+            }
+        },
+        Err(failure) => {
+            // whatever couldn't be put back was changed after all
+            for &i in &failure.not_undone {
+                let (bytes, note) = outcomes[i].clone();
+                let entry = &mut report.entries[i];
+                entry.status = Status::Done;
+                entry.bytes = bytes;
+                entry.note = Some(match note {
+                    Some(note) => format!("{}, couldn't be undone", note),
+                    None => String::from("couldn't be undone"),
+                });
+            }
+            report.entries[failure.entry].status = Status::Failed;
+            report.entries[failure.entry].error = Some(failure.error.to_string());
+            match failure.undo_error {
+                None => {
+                    report.rolled_back = true;
+                    if let Err(e) = backup::delete_snapshot(&snapshot) {
+                        report.add_error(format!("couldn't remove the unneeded snapshot: {}", e));
+                    }
+                },
+                Some(e) => {
+                    report.add_error(format!("couldn't undo every change already made ({}), restore the snapshot to put them back", e));
+                    report.snapshot = Some(snapshot.dir);
+                },
+            }
+        },
+    }
+
+    Ok(report)
+}
+
+// where committing a plan's changes went wrong, and what couldn't be put back afterwards
+struct CommitFailure {
+    entry: usize,
+    error: Error,
+    // entries that are still changed because undoing them failed
+    not_undone: Vec<usize>,
+    // the last error from undoing, including cleaning up temporary files
+    undo_error: Option<Error>,
+}
+
+// a plan's changes written to temporary files next to their destinations, ready to be swapped in.
+// being in the same folder means renaming one over its destination replaces it in one go.
+#[derive(Default)]
+struct Staged {
+    // the entry each file belongs to, the temporary file, and where it goes
+    files: Vec<(usize, PathBuf, PathBuf)>,
+    deletes: Vec<(usize, PathBuf)>,
+    // folders that didn't exist before, in the order they were made
+    dirs: Vec<PathBuf>,
+}
+
+impl Staged {
+    // writes what an entry will put in the destination to temporary files.
+    // returns how many bytes that is, and anything worth noting about it.
+    fn stage(&mut self, i: usize, e: &PlanEntry) -> Result<(Option<u64>, Option<String>), Error> {
+        match e.action {
+            Action::Copy | Action::Overwrite => self.stage_file(i, e.src.as_ref().unwrap(), &e.dst, e.transform.as_ref()),
+            Action::Rewrite => match self.stage_file(i, &e.dst, &e.dst, e.transform.as_ref())? {
+                (None, None) => Ok((None, Some(String::from("left as it was")))),
+                staged => Ok(staged),
+            },
+            Action::CreateDir => {
+                self.make_dirs(&e.dst)?;
+                Ok((None, None))
+            },
+            Action::Delete => {
+                self.deletes.push((i, e.dst.clone()));
+                Ok((None, None))
+            },
+            Action::CopyDir => {
+                let src = e.src.as_ref().unwrap();
+                let files = dir_files(src).map_err(at("source", src))?;
+                let mut bytes = 0;
+                for file in &files {
+                    bytes += self.stage_file(i, &src.join(file), &e.dst.join(file), None)?.0.unwrap_or_default();
+                }
+                Ok((Some(bytes), Some(format!("{} files", files.len()))))
+            },
+        }
+    }
+
+    // src, transformed on the way, in a temporary file next to dst. a rewrite that doesn't change
+    // anything isn't staged, and returns no bytes or note.
+    fn stage_file(&mut self, i: usize, src: &Path, dst: &Path, transform: Option<&Transform>) -> Result<(Option<u64>, Option<String>), Error> {
+        // it would only fail once the files are being swapped in
+        if dst.is_dir() {
+            return Err(Error::other(format!("destination {} is a folder, not a file", dst.display())))
+        }
+        if let Some(parent) = dst.parent() {
+            self.make_dirs(parent).map_err(at("destination", parent))?;
+        }
+        let read = fs::read(src).map_err(at("source", src))?;
+        let (contents, note) = match transform {
+            Some(t) => t.apply(read, dst).map_err(at("destination", dst))?,
+            None => (read, None),
+        };
+        if src == dst && note.is_none() {
+            return Ok((None, None))
+        }
+
+        let tmp = staging_path(dst, "new");
+        // added first so a half written file is cleaned up too
+        self.files.push((i, tmp.clone(), dst.to_path_buf()));
+        fs::write(&tmp, &contents).map_err(at("destination", &tmp))?;
+        Ok((Some(contents.len() as u64), note))
+    }
+
+    // makes a folder and any missing parents, remembering which are new so they can be removed again
+    fn make_dirs(&mut self, dir: &Path) -> Result<(), Error> {
+        let missing: Vec<&Path> = dir.ancestors().take_while(|d| !d.exists()).collect();
+        for d in missing.into_iter().rev() {
+            fs::create_dir(d)?;
+            self.dirs.push(d.to_path_buf());
+        }
+        Ok(())
+    }
+
+    // swaps every temporary file in and removes deleted files, keeping copies of the originals until all of
+    // it has gone through. if anything fails, what was done is undone as far as it can be.
+    fn commit(&self) -> Result<(), CommitFailure> {
+        // the entry, the original set aside, and where it goes back to
+        let mut originals: Vec<(usize, PathBuf, PathBuf)> = vec![];
+        // files that weren't there before, by entry
+        let mut added: Vec<(usize, PathBuf)> = vec![];
+        let mut failure: Option<(usize, Error)> = None;
+
+        for (i, tmp, dst) in &self.files {
+            if let Err(e) = swap_in(*i, tmp, dst, &mut originals, &mut added) {
+                failure = Some((*i, e));
+                break
+            }
+        }
+        if failure.is_none() {
+            for (i, dst) in &self.deletes {
+                let old = staging_path(dst, "old");
+                match fs::rename(dst, &old) {
+                    Ok(()) => originals.push((*i, old, dst.clone())),
+                    Err(e) => {
+                        failure = Some((*i, at("destination", dst)(e)));
+                        break
+                    },
+                }
+            }
+        }
+
+        let (entry, error) = match failure {
+            Some(f) => f,
+            None => {
+                // the copy has gone through either way, so a leftover original isn't worth failing over
+                for (_, old, _) in &originals {
+                    let _ = fs::remove_file(old);
+                }
+                return Ok(())
+            },
+        };
+
+        let mut failure = CommitFailure { entry, error, not_undone: vec![], undo_error: None };
+        for (i, dst) in &added {
+            if let Err(e) = fs::remove_file(dst) {
+                failure.not_undone.push(*i);
+                failure.undo_error = Some(e);
+            }
+        }
+        for (i, old, dst) in originals.iter().rev() {
+            if let Err(e) = fs::rename(old, dst) {
+                failure.not_undone.push(*i);
+                failure.undo_error = Some(e);
+            }
+        }
+        if let Err(e) = self.discard() {
+            failure.undo_error = Some(e);
+        }
+        failure.not_undone.sort_unstable();
+        failure.not_undone.dedup();
+        failure.not_undone.retain(|i| *i != entry);
+        Err(failure)
+    }
+
+    // removes whatever temporary files and new folders are left, carrying on past errors and returning the last
+    fn discard(&self) -> Result<(), Error> {
+        let mut result = Ok(());
+        for (_, tmp, _) in &self.files {
+            match fs::remove_file(tmp) {
+                Err(e) if e.kind() != ErrorKind::NotFound => result = Err(e),
+                _ => (),
+            }
+        }
+        for dir in self.dirs.iter().rev() {
+            if let Err(e) = fs::remove_dir(dir) {
+                result = Err(e);
+            }
+        }
+        result
+    }
+}
+
+// replaces dst with tmp in one rename, after copying what was there aside, recording both under entry `i`
+fn swap_in(i: usize, tmp: &Path, dst: &Path, originals: &mut Vec<(usize, PathBuf, PathBuf)>, added: &mut Vec<(usize, PathBuf)>) -> Result<(), Error> {
+    let existed = dst.try_exists().map_err(at("destination", dst))?;
+    if existed {
+        let old = staging_path(dst, "old");
+        fs::copy(dst, &old).map_err(at("destination", dst))?;
+        originals.push((i, old, dst.to_path_buf()));
+    }
+    fs::rename(tmp, dst).map_err(at("destination", dst))?;
+    if !existed {
+        added.push((i, dst.to_path_buf()));
+    }
+    Ok(())
+}
+
+// adds the file an error is about, and which side of the copy it's on, to the error's message
+fn at<'a>(side: &'a str, path: &'a Path) -> impl FnOnce(Error) -> Error + 'a {
+    move |e| Error::new(e.kind(), format!("{} {}: {}", side, path.display(), e))
+}
+
+// a hidden file next to `path` for staging a copy, e.g. .config-cache.wtf.profile-copy-new
+fn staging_path(path: &Path, kind: &str) -> PathBuf {
+    let mut name = OsString::from(".");
+    name.push(path.file_name().unwrap_or_default());
+    name.push(".profile-copy-");
+    name.push(kind);
+    path.with_file_name(name)
+}
+
+// every file under a folder, relative to it
+fn dir_files(dir: &Path) -> Result<Vec<PathBuf>, Error> {
+    let mut files: Vec<PathBuf> = vec![];
+    let mut dirs: Vec<PathBuf> = vec![PathBuf::new()];
+
+    while let Some(rel) = dirs.pop() {
+        for entry in fs::read_dir(dir.join(&rel))? {
+            let entry = entry?;
+            let path = rel.join(entry.file_name());
+            if entry.file_type()?.is_dir() {
+                dirs.push(path);
+            } else {
+                files.push(path);
+            }
+        }
+    }
+
+    files.sort();
+    Ok(files)
+}
+
+// returns the rewritten file and how many profileKeys tables changed.
+// files that can't be parsed are left as they are.
+fn apply_rewrite(contents: Vec<u8>, rewrite: &KeyRewrite) -> (Vec<u8>, usize) {
+    let mut sv = match SavedVariables::parse(&contents) {
+        Ok(sv) => sv,
+        Err(_) => return (contents, 0),
+    };
+
+    match profiles::rewrite_profile_keys(&mut sv, &rewrite.from, &rewrite.to) {
+        0 => (contents, 0),
+        changed => (sv.to_bytes(), changed),
+    }
+}
+
+// warns about picked bindings that will replace different ones on the target,
+// and picked macros that won't fit in the target's free slots
+fn note_merge_problems(plan: &mut CopyPlan, src: &Path, dst: &Path, transform: Option<&Transform>) -> Result<(), Error> {
+    if !src.try_exists()? {
+        return Ok(())
+    }
+    match transform {
+        Some(Transform::MergeBindings(keys)) => {
+            let (_, replaced) = bindings::merge(&or_empty(BindingsCache::read(dst))?, &BindingsCache::read(src)?, keys);
+            for c in replaced {
+                plan.notes.push(format!("binding conflict: {}", c));
+            }
+        },
+        Some(Transform::MergeMacros(scope, slots, names)) => {
+            let (_, report) = macros::merge(&or_empty(MacrosCache::read(dst))?, &MacrosCache::read(src)?, names, *slots);
+            if !report.overflow.is_empty() {
+                plan.notes.push(format!("the target only has {} {} macro slots, leaving out: {}", slots, scope, report.overflow.join(", ")));
+            }
+        },
+        _ => (),
+    }
+    Ok(())
+}
+
+// how many macros a version of the given flavor keeps at a scope
+pub fn macro_slots(scope: Scope, flavor: addons::Flavor) -> usize {
+    match scope {
+        Scope::Account => macros::account_macro_slots(flavor),
+        Scope::Character => macros::CHARACTER_MACRO_SLOTS,
+    }
+}
+
+// a destination file that isn't there yet counts as empty
+fn or_empty<T: Default>(read: Result<T, Error>) -> Result<T, Error> {
+    match read {
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(T::default()),
+        result => result,
+    }
+}
+
+// adds a copy to the plan, or a note if there's nothing to copy
+fn plan_file(plan: &mut CopyPlan, src: PathBuf, dst: PathBuf, transform: Option<Transform>) -> Result<(), Error> {
+    if !src.try_exists()? {
+        plan.notes.push(format!("source missing, skipping: {:?}", src.as_os_str()));
+        return Ok(())
+    }
+    let mut entry = PlanEntry::copy(src, dst)?;
+    entry.transform = transform;
+    plan.entries.push(entry);
+    Ok(())
+}
+
+// a plan that puts a snapshot's files back where they were taken from, and removes the ones that didn't exist
+// before. it's carried out like any copy, so it's all or nothing, and takes a snapshot of its own first.
+pub fn restore_plan(snapshot: &backup::Snapshot) -> Result<CopyPlan, Error> {
+    let version_dir = snapshot.version_dir()?;
+    let mut plan = CopyPlan {
+        dst_version: version_dir.to_path_buf(),
+        dst_root: version_dir.join("WTF").join("Account").join(&snapshot.wtf.account),
+        dst_wtf: snapshot.wtf.clone(),
+        entries: vec![],
+        notes: vec![format!("restoring the snapshot from {}", backup::format_timestamp(snapshot.created))],
+    };
+    for file in &snapshot.files {
+        let dst = version_dir.join(&file.path);
+        if file.existed {
+            plan.entries.push(PlanEntry::copy(snapshot.dir.join(&file.path), dst)?);
+        } else {
+            plan_delete(&mut plan, dst)?;
+        }
+    }
+    Ok(plan)
+}
+
+fn plan_delete(plan: &mut CopyPlan, dst: PathBuf) -> Result<(), Error> {
+    if dst.try_exists()? {
+        plan.entries.push(PlanEntry::delete(dst)?);
+    }
+    Ok(())
+}
+
+fn account_root(install_dir: &Path, ver: &OsStr, wtf: &Wtf) -> PathBuf {
+    install_dir
+        .join(ver)
+        .join("WTF")
+        .join("Account")
+        .join(&wtf.account)
+}
+
+// every addon with SavedVariables for a character, account-wide ones first
+pub fn list_saved_variables(install_dir: &Path, ver: &OsStr, wtf: &Wtf) -> Result<Vec<SavedVariablesFile>, Error> {
+    let root = account_root(install_dir, ver, wtf);
+    let mut files: Vec<SavedVariablesFile> = vec![];
+
+    let dirs = [
+        (Scope::Account, root.join("SavedVariables")),
+        (Scope::Character, root.join(&wtf.realm).join(&wtf.character).join("SavedVariables")),
+    ];
+    for (scope, dir) in dirs {
+        if !dir.is_dir() {
+            continue
+        }
+        files.extend(lua_files(&dir)?.into_iter().map(|file_name| SavedVariablesFile { scope, file_name }));
+    }
+
+    Ok(files)
+}
+
+// a character's config-cache.wtf at either scope, or None if it doesn't have one
+pub fn read_config_cache(install_dir: &Path, ver: &OsStr, wtf: &Wtf, scope: Scope) -> Result<Option<ConfigCache>, Error> {
+    or_none(ConfigCache::read(&scope_dir(install_dir, ver, wtf, scope).join("config-cache.wtf")))
+}
+
+// a character's macros-cache.txt at either scope, or None if it doesn't have one
+pub fn read_macros(install_dir: &Path, ver: &OsStr, wtf: &Wtf, scope: Scope) -> Result<Option<MacrosCache>, Error> {
+    or_none(MacrosCache::read(&scope_dir(install_dir, ver, wtf, scope).join("macros-cache.txt")))
+}
+
+// the account folder, or the character's folder in it
+fn scope_dir(install_dir: &Path, ver: &OsStr, wtf: &Wtf, scope: Scope) -> PathBuf {
+    let root = account_root(install_dir, ver, wtf);
+    match scope {
+        Scope::Account => root,
+        Scope::Character => root.join(&wtf.realm).join(&wtf.character),
+    }
+}
+
+fn or_none<T>(read: Result<T, Error>) -> Result<Option<T>, Error> {
+    match read {
+        Ok(t) => Ok(Some(t)),
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+// the key bindings a character uses and where they're kept: its own if it has character specific
+// bindings, otherwise the account's. None if it doesn't have any saved.
+pub fn read_bindings(install_dir: &Path, ver: &OsStr, wtf: &Wtf) -> Result<Option<(Scope, BindingsCache)>, Error> {
+    for scope in [Scope::Character, Scope::Account] {
+        if let Some(b) = or_none(BindingsCache::read(&scope_dir(install_dir, ver, wtf, scope).join(BINDINGS_FILE)))? {
+            return Ok(Some((scope, b)))
+        }
+    }
+    Ok(None)
+}
+
+// names of the .lua files in a SavedVariables folder
+pub fn lua_files(dir: &Path) -> Result<Vec<OsString>, Error> {
+    let mut entries = fs::read_dir(dir)?
+    .collect::<Result<Vec<_>, Error>>()?;
+    entries.sort_by_key(|e| e.file_name());
+
+    Ok(entries
+        .iter()
+        .filter(|e| e.path().extension().and_then(|n| n.to_str()) == Some("lua"))
+        .map(|e| e.file_name())
+        .collect())
+}
+
+fn format_size(bytes: u64) -> String {
+    if bytes < 1024 {
+        return format!("{} B", bytes)
+    }
+    let mut size = bytes as f64;
+    for unit in ["KiB", "MiB", "GiB"] {
+        size /= 1024.0;
+        if size < 1024.0 {
+            return format!("{:.1} {}", size, unit)
+        }
+    }
+    format!("{:.1} TiB", size / 1024.0)
+}
+
+fn format_time(t: SystemTime) -> String {
+    match t.duration_since(UNIX_EPOCH) {
+        Ok(d) => backup::format_timestamp(d.as_secs()),
+        Err(_) => String::from("unknown"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // an empty folder of its own for a test, which the test removes again
+    fn scratch(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("wow-profile-copy-ng-copy-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn write(path: &Path, contents: &str) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, contents).unwrap();
+    }
+
+    fn wtf(account: &str, realm: &str, character: &str) -> Wtf {
+        Wtf {
+            account: OsString::from(account),
+            realm: OsString::from(realm),
+            character: OsString::from(character),
+            has_vars: false,
+        }
+    }
+
+    fn plan(version: &Path, entries: Vec<PlanEntry>) -> CopyPlan {
+        CopyPlan {
+            dst_version: version.to_path_buf(),
+            dst_root: version.join("WTF").join("Account").join("ACC"),
+            dst_wtf: wtf("ACC", "Realm", "Char"),
+            entries,
+            notes: vec![],
+        }
+    }
+
+    const PROFILES: &str = "\nMyAddonDB = {\n\t[\"profileKeys\"] = {\n\t\t[\"Main - Area 52\"] = \"Main - Area 52\",\n\t},\n}\n";
+
+    // a retail install with two accounts on Area 52. ACC1 has Main, Bound and Alt, ACC2 has Other and Own.
+    // Bound and Own have character specific key bindings.
+    fn install(dir: &Path) {
+        let accounts = dir.join("_retail_").join("WTF").join("Account");
+        for (account, characters) in [("ACC1", ["Main", "Bound", "Alt"].as_slice()), ("ACC2", ["Other", "Own"].as_slice())] {
+            let account = accounts.join(account);
+            for file in ACCOUNT_FILES {
+                write(&account.join(file), "");
+            }
+            write(&account.join("SavedVariables").join("MyAddon.lua"), PROFILES);
+            write(&account.join("SavedVariables").join("Plain.lua"), "\nPlainDB = {\n}\n");
+            for character in characters {
+                write(&account.join("Area 52").join(character).join("config-cache.wtf"), "");
+                write(&account.join("Area 52").join(character).join("SavedVariables").join("MyAddon.lua"), "");
+            }
+        }
+        write(&accounts.join("ACC1").join("Area 52").join("Bound").join(BINDINGS_FILE), "");
+        write(&accounts.join("ACC2").join("Area 52").join("Own").join(BINDINGS_FILE), "");
+    }
+
+    fn account(dir: &Path, account: &str) -> PathBuf {
+        dir.join("_retail_").join("WTF").join("Account").join(account)
+    }
+
+    fn request(dir: &Path, from: &str, account: &str, to: &str) -> CopyRequest {
+        CopyRequest::new(dir, "_retail_", wtf("ACC1", "Area 52", from), "_retail_", wtf(account, "Area 52", to))
+    }
+
+    // what the plan does to a destination file, if anything
+    fn action(plan: &CopyPlan, dst: &Path) -> Option<Action> {
+        plan.entries.iter().find(|e| e.dst == dst).map(|e| e.action)
+    }
+
+    // every file under a folder, hidden ones included, with what's in it
+    fn contents(dir: &Path) -> Vec<(PathBuf, Vec<u8>)> {
+        dir_files(dir).unwrap().into_iter()
+            .map(|f| {
+                let c = fs::read(dir.join(&f)).unwrap();
+                (f, c)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn same_account_only_rewrites_profile_keys() {
+        let dir = scratch("same-account");
+        install(&dir);
+        let plan = plan_copy(&request(&dir, "Main", "ACC1", "Alt"));
+        fs::remove_dir_all(&dir).unwrap();
+        let plan = plan.unwrap();
+        let acc1 = account(&dir, "ACC1");
+
+        // the account's files are shared, so the alt only needs pointing at the main's profiles
+        assert!(plan.notes.contains(&String::from("skipping account copy.")));
+        assert_eq!(action(&plan, &acc1.join("config-cache.wtf")), None);
+        let vars: Vec<(Action, &Path)> = plan.entries.iter()
+            .filter(|e| e.dst.starts_with(acc1.join("SavedVariables")))
+            .map(|e| (e.action, e.dst.as_path()))
+            .collect();
+        assert_eq!(vars, [(Action::Rewrite, acc1.join("SavedVariables").join("MyAddon.lua").as_path())]);
+        assert_eq!(action(&plan, &acc1.join("Area 52").join("Alt").join("config-cache.wtf")), Some(Action::Overwrite));
+    }
+
+    #[test]
+    fn batch_copies_account_files_once() {
+        let dir = scratch("batch");
+        install(&dir);
+        let plans = plan_batch(&[request(&dir, "Main", "ACC2", "Other"), request(&dir, "Main", "ACC2", "Own"), request(&dir, "Main", "ACC1", "Alt")]);
+        fs::remove_dir_all(&dir).unwrap();
+        let plans = plans.unwrap();
+        let (acc1, acc2) = (account(&dir, "ACC1"), account(&dir, "ACC2"));
+
+        assert_eq!(action(&plans[0], &acc2.join("config-cache.wtf")), Some(Action::Overwrite));
+        assert_eq!(action(&plans[0], &acc2.join("SavedVariables").join("Plain.lua")), Some(Action::Overwrite));
+        // the account was copied for Other already, so Own only needs pointing at the source's profiles
+        assert!(plans[1].notes.contains(&String::from("skipping account copy.")));
+        assert_eq!(action(&plans[1], &acc2.join("config-cache.wtf")), None);
+        assert_eq!(action(&plans[1], &acc2.join("SavedVariables").join("Plain.lua")), None);
+        assert_eq!(action(&plans[1], &acc2.join("SavedVariables").join("MyAddon.lua")), Some(Action::Rewrite));
+        // the source's own account is never copied over itself
+        assert_eq!(action(&plans[2], &acc1.join("SavedVariables").join("MyAddon.lua")), Some(Action::Rewrite));
+        // every character gets its own files
+        assert_eq!(action(&plans[1], &acc2.join("Area 52").join("Own").join("config-cache.wtf")), Some(Action::Overwrite));
+        assert_eq!(action(&plans[2], &acc1.join("Area 52").join("Alt").join("config-cache.wtf")), Some(Action::Overwrite));
+    }
+
+    #[test]
+    fn character_bindings() {
+        let dir = scratch("bindings");
+        install(&dir);
+        let (acc1, acc2) = (account(&dir, "ACC1"), account(&dir, "ACC2"));
+        let bound = acc1.join("Area 52").join("Bound").join(BINDINGS_FILE);
+        // source and target character, and where the target character's own bindings come from
+        let cases = [
+            ("Main", "Other", None),
+            ("Bound", "Other", Some(bound.clone())),
+            ("Bound", "Own", Some(bound.clone())),
+            ("Main", "Own", Some(acc1.join(BINDINGS_FILE))),
+        ];
+        let plans: Vec<CopyPlan> = cases.iter().map(|(from, to, _)| plan_copy(&request(&dir, from, "ACC2", to)).unwrap()).collect();
+        let mut merge = request(&dir, "Bound", "ACC2", "Other");
+        merge.merge_bindings = Some(BTreeSet::from([String::from("SPACE")]));
+        let merged = plan_copy(&merge);
+        fs::remove_dir_all(&dir).unwrap();
+        let merged = merged.unwrap();
+
+        for (plan, (from, to, src)) in plans.iter().zip(&cases) {
+            let dst = acc2.join("Area 52").join(to).join(BINDINGS_FILE);
+            let entry = plan.entries.iter().find(|e| e.dst == dst);
+            assert_eq!(entry.and_then(|e| e.src.as_ref()), src.as_ref(), "{} to {}", from, to);
+            // the account's are copied as usual
+            assert_eq!(action(plan, &acc2.join(BINDINGS_FILE)), Some(Action::Overwrite), "{} to {}", from, to);
+        }
+        // merging the source character's own leaves the account's alone
+        assert_eq!(action(&merged, &acc2.join(BINDINGS_FILE)), None);
+        let entry = merged.entries.iter().find(|e| e.dst == acc2.join("Area 52").join("Other").join(BINDINGS_FILE)).unwrap();
+        assert_eq!(entry.src.as_ref(), Some(&bound));
+        assert!(matches!(entry.transform, Some(Transform::MergeBindings(_))));
+    }
+
+    #[test]
+    fn same_account_in_another_install() {
+        let dir = scratch("other-install");
+        install(&dir.join("a"));
+        install(&dir.join("b"));
+        let mut req = request(&dir.join("a"), "Main", "ACC1", "Alt");
+        req.dst_install_dir = dir.join("b");
+        let plan = plan_copy(&req);
+        fs::remove_dir_all(&dir).unwrap();
+        let plan = plan.unwrap();
+        let (src, dst) = (account(&dir.join("a"), "ACC1"), account(&dir.join("b"), "ACC1"));
+
+        // a different folder despite the name, so its files are copied like any other account's
+        assert!(!plan.notes.contains(&String::from("skipping account copy.")));
+        assert_eq!(action(&plan, &dst.join("config-cache.wtf")), Some(Action::Overwrite));
+        let vars = plan.entries.iter().find(|e| e.dst == dst.join("SavedVariables").join("MyAddon.lua")).unwrap();
+        assert_eq!(vars.src, Some(src.join("SavedVariables").join("MyAddon.lua")));
+        assert_eq!(vars.transform, Some(Transform::ProfileKeys(KeyRewrite { from: String::from("Main - Area 52"), to: String::from("Alt - Area 52") })));
+        assert_eq!(action(&plan, &dst.join("SavedVariables").join("Plain.lua")), Some(Action::Overwrite));
+    }
+
+    #[test]
+    fn batch_copies_addon_folders_once() {
+        let dir = scratch("addons");
+        install(&dir);
+        write(&addons::addons_dir(&dir.join("_retail_")).join("MyAddon").join("MyAddon.toc"), "## Interface: 110002\n");
+        let to_ptr = |character: &str| {
+            let mut req = request(&dir, "Main", "ACC1", character);
+            req.dst_ver = OsString::from("_ptr_");
+            req.sync_addons = BTreeSet::from([String::from("MyAddon")]);
+            req
+        };
+        let plans = plan_batch(&[to_ptr("Main"), to_ptr("Alt")]);
+        let mut same = to_ptr("Alt");
+        same.dst_ver = OsString::from("_retail_");
+        let same = plan_copy(&same);
+        fs::remove_dir_all(&dir).unwrap();
+        let (plans, same) = (plans.unwrap(), same.unwrap());
+
+        let addon = addons::addons_dir(&dir.join("_ptr_")).join("MyAddon");
+        assert_eq!(action(&plans[0], &addon), Some(Action::CopyDir));
+        assert_eq!(action(&plans[1], &addon), None);
+        // within a version both characters already share the folder
+        assert!(same.entries.iter().all(|e| e.action != Action::CopyDir));
+        assert!(same.notes.contains(&String::from("skipping addon folders, the source and target are the same version.")));
+    }
+
+    #[test]
+    fn rewrite_leaves_unchanged_files_as_they_were() {
+        let rewrite = KeyRewrite { from: String::from("Main - Area 52"), to: String::from("Alt - Area 52") };
+        // written differently from how the writer would put it back
+        let file = b"MyAddonDB = { [\"profileKeys\"] = { [\"Other - Area 52\"] = \"Default\" } }".to_vec();
+        assert_eq!(apply_rewrite(file.clone(), &rewrite), (file, 0));
+        let broken = b"MyAddonDB = {".to_vec();
+        assert_eq!(apply_rewrite(broken.clone(), &rewrite), (broken, 0));
+    }
+
+    #[test]
+    fn staging_failure_changes_nothing() {
+        let dir = scratch("staging");
+        let version = dir.join("_retail_");
+        let dst = version.join("WTF").join("Account").join("ACC");
+        write(&dst.join("a.txt"), "old a");
+        write(&dst.join("b.txt"), "old b");
+        for name in ["a.txt", "b.txt", "c.txt"] {
+            write(&dir.join("src").join(name), &format!("new {}", name));
+        }
+        let plan = plan(&version, vec![
+            PlanEntry::copy(dir.join("src").join("a.txt"), dst.join("a.txt")).unwrap(),
+            PlanEntry::copy(dir.join("src").join("c.txt"), dst.join("new").join("c.txt")).unwrap(),
+            PlanEntry::copy(dir.join("src").join("b.txt"), dst.join("b.txt")).unwrap(),
+        ]);
+        // gone by the time it's staged
+        fs::remove_file(dir.join("src").join("b.txt")).unwrap();
+
+        let before = contents(&dst);
+        let report = execute_plan(&plan).unwrap();
+        let after = contents(&dst);
+        let new_dir = dst.join("new").exists();
+        let snapshots = fs::read_dir(version.join(backup::SNAPSHOT_DIR)).unwrap().count();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert!(report.rolled_back);
+        assert_eq!(report.error, None);
+        assert_eq!(report.snapshot, None);
+        let statuses: Vec<Status> = report.entries.iter().map(|e| e.status).collect();
+        assert_eq!(statuses, [Status::Skipped, Status::Skipped, Status::Failed]);
+        assert!(report.entries[2].error.as_ref().unwrap().starts_with("source "));
+        // no temporary files or folders left behind, and the snapshot isn't kept
+        assert_eq!(after, before);
+        assert!(!new_dir);
+        assert_eq!(snapshots, 0);
+    }
+
+    #[test]
+    fn commit_failure_restores_originals() {
+        let dir = scratch("commit");
+        let dst = dir.join("dst");
+        write(&dst.join("a.txt"), "old a");
+        write(&dst.join("b.txt"), "old b");
+        for name in ["a.txt", "b.txt", "c.txt"] {
+            write(&dir.join("src").join(name), &format!("new {}", name));
+        }
+        let entries = [
+            PlanEntry::copy(dir.join("src").join("a.txt"), dst.join("a.txt")).unwrap(),
+            PlanEntry::copy(dir.join("src").join("c.txt"), dst.join("c.txt")).unwrap(),
+            PlanEntry::copy(dir.join("src").join("b.txt"), dst.join("b.txt")).unwrap(),
+        ];
+        let mut staged = Staged::default();
+        for (i, e) in entries.iter().enumerate() {
+            staged.stage(i, e).unwrap();
+        }
+        // something else puts a folder in the way after b.txt was staged
+        fs::remove_file(dst.join("b.txt")).unwrap();
+        write(&dst.join("b.txt").join("in the way"), "");
+
+        let failure = staged.commit().unwrap_err();
+        let a = fs::read_to_string(dst.join("a.txt")).unwrap();
+        let c = dst.join("c.txt").exists();
+        let left: Vec<PathBuf> = dir_files(&dst).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(failure.entry, 2);
+        assert!(failure.not_undone.is_empty());
+        assert!(failure.undo_error.is_none());
+        assert_eq!(a, "old a");
+        assert!(!c);
+        assert_eq!(left, [PathBuf::from("a.txt"), Path::new("b.txt").join("in the way")]);
+    }
+
+    #[test]
+    fn counts_not_undone() {
+        let dir = Path::new("/nonexistent");
+        let report = |statuses: &[Status]| {
+            let entries = statuses.iter().map(|_| PlanEntry::create_dir(dir.join("x"))).collect();
+            let mut report = CopyReport::unfinished(&plan(dir, entries), Status::Skipped);
+            for (e, status) in report.entries.iter_mut().zip(statuses) {
+                e.status = *status;
+            }
+            report
+        };
+        // the two done alongside a failure couldn't be undone, the ones in a report without one are fine
+        let reports = [
+            report(&[Status::Done, Status::Failed, Status::Done, Status::Skipped]),
+            report(&[Status::Done, Status::Done]),
+            report(&[Status::Skipped]),
+        ];
+        assert_eq!(Counts::of(&reports), Counts { done: 4, failed: 1, skipped: 2, not_undone: 2 });
+        assert_eq!(reports[1].counts(), Counts { done: 2, failed: 0, skipped: 0, not_undone: 0 });
+    }
+
+    #[test]
+    fn restore_round_trip() {
+        let dir = scratch("restore");
+        let version = dir.join("_retail_");
+        let dst = version.join("WTF").join("Account").join("ACC");
+        write(&dst.join("a.txt"), "old a");
+        write(&dst.join("Realm").join("Char").join("b.txt"), "old b");
+        let files = [dst.join("a.txt"), dst.join("Realm").join("Char").join("b.txt"), dst.join("SavedVariables").join("c.lua")];
+        let before = contents(&dst);
+
+        let snapshot = backup::take_snapshot(&version, &wtf("ACC", "Realm", "Char"), &files).unwrap();
+        write(&files[0], "changed a");
+        fs::remove_file(&files[1]).unwrap();
+        write(&files[2], "new c");
+
+        let restore = restore_plan(&snapshot).unwrap();
+        let actions: Vec<Action> = restore.entries.iter().map(|e| e.action).collect();
+        let report = execute_plan(&restore).unwrap();
+        let after = contents(&dst);
+        let listed = backup::list_snapshots(&crate::wow::Install {
+            install_dir: dir.clone().into_os_string(),
+            versions: vec![crate::wow::Version { name: OsString::from("_retail_"), wtfs: vec![] }],
+        }).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(actions, [Action::Overwrite, Action::Copy, Action::Delete]);
+        assert_eq!(report.counts(), Counts { done: 3, failed: 0, skipped: 0, not_undone: 0 });
+        assert_eq!(after, before);
+        // restoring takes a snapshot of its own, so it can be undone too
+        assert_eq!(listed.len(), 2);
+        let taken = report.snapshot.unwrap();
+        assert_ne!(taken, snapshot.dir);
+        assert!(listed.iter().any(|s| s.dir == taken));
+    }
+}
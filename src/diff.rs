@@ -0,0 +1,184 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+// side-by-side line diffs between a source file and the destination file it would replace
+
+use similar::{Algorithm, DiffOp, TextDiff};
+use std::{fs, io::{Error, ErrorKind}, path::{Path, PathBuf}, time::Duration};
+
+// lines of unchanged context kept around each change
+const CONTEXT: usize = 3;
+// past this, large SavedVariables files would take too long to diff exactly
+const TIMEOUT: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RowKind {
+    Equal,
+    Changed,
+    // only in the source, so the copy would add it
+    Added,
+    // only in the destination, so the copy would remove it
+    Removed,
+    // stands in for unchanged lines that were left out
+    Skipped,
+}
+
+// a line number and its text
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Line {
+    pub number: usize,
+    pub text: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiffRow {
+    pub kind: RowKind,
+    pub src: Option<Line>,
+    pub dst: Option<Line>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileDiff {
+    pub src: PathBuf,
+    pub dst: PathBuf,
+    pub rows: Vec<DiffRow>,
+}
+
+impl FileDiff {
+    pub fn is_identical(&self) -> bool {
+        self.rows.iter().all(|r| r.kind == RowKind::Equal)
+    }
+}
+
+// diffs src against dst. a missing file on either side counts as empty.
+pub fn diff_files(src: &Path, dst: &Path) -> Result<FileDiff, Error> {
+    let new = match fs::read(src) {
+        Ok(bytes) => bytes,
+        Err(e) if e.kind() == ErrorKind::NotFound => vec![],
+        Err(e) => return Err(e),
+    };
+    diff_contents(src, &new, dst)
+}
+
+// diffs what's about to be written to dst (which came from src) against what's there now
+pub fn diff_contents(src: &Path, new: &[u8], dst: &Path) -> Result<FileDiff, Error> {
+    let old = read_lossy(dst)?;
+    let new = String::from_utf8_lossy(new);
+
+    let diff = TextDiff::configure()
+        .algorithm(Algorithm::Myers)
+        .timeout(TIMEOUT)
+        .diff_lines(old.as_str(), new.as_ref());
+
+    let old_lines: Vec<&str> = diff.old_slices().to_vec();
+    let new_lines: Vec<&str> = diff.new_slices().to_vec();
+    let line = |lines: &[&str], i: usize| Line {
+        number: i + 1,
+        text: lines[i].trim_end_matches(['\r', '\n']).to_owned(),
+    };
+
+    let mut rows: Vec<DiffRow> = vec![];
+    for (n, group) in diff.grouped_ops(CONTEXT).iter().enumerate() {
+        if n > 0 || group.first().is_some_and(|op| op.old_range().start > 0) {
+            rows.push(DiffRow { kind: RowKind::Skipped, src: None, dst: None });
+        }
+
+        for op in group {
+            match *op {
+                DiffOp::Equal { old_index, new_index, len } => {
+                    for i in 0..len {
+                        rows.push(DiffRow {
+                            kind: RowKind::Equal,
+                            src: Some(line(&new_lines, new_index + i)),
+                            dst: Some(line(&old_lines, old_index + i)),
+                        });
+                    }
+                },
+                DiffOp::Delete { old_index, old_len, .. } => {
+                    for i in 0..old_len {
+                        rows.push(DiffRow {
+                            kind: RowKind::Removed,
+                            src: None,
+                            dst: Some(line(&old_lines, old_index + i)),
+                        });
+                    }
+                },
+                DiffOp::Insert { new_index, new_len, .. } => {
+                    for i in 0..new_len {
+                        rows.push(DiffRow {
+                            kind: RowKind::Added,
+                            src: Some(line(&new_lines, new_index + i)),
+                            dst: None,
+                        });
+                    }
+                },
+                // pair replaced lines up so they sit next to each other
+                DiffOp::Replace { old_index, old_len, new_index, new_len } => {
+                    for i in 0..old_len.max(new_len) {
+                        rows.push(DiffRow {
+                            kind: RowKind::Changed,
+                            src: (i < new_len).then(|| line(&new_lines, new_index + i)),
+                            dst: (i < old_len).then(|| line(&old_lines, old_index + i)),
+                        });
+                    }
+                },
+            }
+        }
+    }
+
+    let unchanged_tail = diff.ops().last().is_some_and(|op| matches!(op, DiffOp::Equal { len, .. } if *len > CONTEXT));
+    if unchanged_tail && rows.iter().any(|r| r.kind != RowKind::Equal) {
+        rows.push(DiffRow { kind: RowKind::Skipped, src: None, dst: None });
+    }
+
+    Ok(FileDiff {
+        src: src.to_path_buf(),
+        dst: dst.to_path_buf(),
+        rows,
+    })
+}
+
+fn read_lossy(path: &Path) -> Result<String, Error> {
+    match fs::read(path) {
+        Ok(bytes) => Ok(String::from_utf8_lossy(&bytes).into_owned()),
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(String::new()),
+        Err(e) => Err(e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hunks() {
+        let dst = std::env::temp_dir().join(format!("wow-profile-copy-ng-diff-{}", std::process::id()));
+        fs::write(&dst, "a\nb\nc\nd\ne\nf\ng\nh\ni\nj\nk\nl\nm\nn\n").unwrap();
+        let diff = diff_contents(Path::new("src"), b"a\nB\nc\nd\ne\nf\ng\nh\ni\nj\nk\nl\nn\n", &dst);
+        let same = diff_contents(Path::new("src"), &fs::read(&dst).unwrap(), &dst);
+        fs::remove_file(&dst).unwrap();
+
+        // each change with three lines of context, and the unchanged lines between them left out
+        let rows: Vec<(RowKind, Option<usize>, Option<usize>)> = diff.unwrap().rows
+            .iter()
+            .map(|r| (r.kind, r.src.as_ref().map(|l| l.number), r.dst.as_ref().map(|l| l.number)))
+            .collect();
+        assert_eq!(rows, [
+            (RowKind::Equal, Some(1), Some(1)),
+            (RowKind::Changed, Some(2), Some(2)),
+            (RowKind::Equal, Some(3), Some(3)),
+            (RowKind::Equal, Some(4), Some(4)),
+            (RowKind::Equal, Some(5), Some(5)),
+            (RowKind::Skipped, None, None),
+            (RowKind::Equal, Some(10), Some(10)),
+            (RowKind::Equal, Some(11), Some(11)),
+            (RowKind::Equal, Some(12), Some(12)),
+            (RowKind::Removed, None, Some(13)),
+            (RowKind::Equal, Some(13), Some(14)),
+        ]);
+        assert!(same.unwrap().is_identical());
+    }
+}
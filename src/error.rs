@@ -0,0 +1,71 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+// what can go wrong finding an install or copying, split up so the GUI can explain each one
+
+use crate::client::RunningClient;
+use std::{io::{self, ErrorKind}, path::PathBuf};
+
+#[derive(Debug)]
+pub enum Error {
+    // the folder has no version folders like _retail_ in it
+    NotAnInstall(PathBuf),
+    // there are version folders, but none has a WTF/Account folder. the game makes it on first login.
+    NoAccounts(PathBuf),
+    // usually the game or another program has the file open, or the folder belongs to another user
+    PermissionDenied(io::Error),
+    // some of a copy's changes failed. each character's changes are all or nothing,
+    // so the others that were made are for other characters in a batch.
+    // `not_undone` are changes that were meant to be undone because of a failure, but couldn't be.
+    PartialCopy { failed: usize, total: usize, not_undone: usize },
+    // the game is open in a version being copied to, and would write over the copy when it exits
+    ClientRunning(Vec<RunningClient>),
+    Io(io::Error),
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::NotAnInstall(dir) => write!(f, "{:?} isn't a WoW install, it has no version folders like _retail_", dir.as_os_str()),
+            Error::NoAccounts(dir) => write!(f, "{:?} has no characters yet, none of its versions have a WTF/Account folder", dir.as_os_str()),
+            Error::PermissionDenied(e) => write!(f, "permission denied: {}", e),
+            Error::PartialCopy { failed, total, not_undone: 0 } => write!(f, "{} of {} changes failed", failed, total),
+            Error::PartialCopy { failed, total, not_undone } => write!(f, "{} of {} changes failed, and {} made before the failure couldn't be undone", failed, total, not_undone),
+            Error::ClientRunning(clients) => write!(f, "WoW is running, exit the game before copying: {}",
+                clients.iter().map(RunningClient::to_string).collect::<Vec<_>>().join(", ")),
+            Error::Io(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::PermissionDenied(e) | Error::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        match e.kind() {
+            ErrorKind::PermissionDenied => Error::PermissionDenied(e),
+            _ => Error::Io(e),
+        }
+    }
+}
+
+// for code that still deals in io errors, like reading presets
+impl From<Error> for io::Error {
+    fn from(e: Error) -> Self {
+        match e {
+            Error::PermissionDenied(e) | Error::Io(e) => e,
+            Error::NotAnInstall(_) | Error::NoAccounts(_) => io::Error::new(ErrorKind::NotFound, e),
+            Error::PartialCopy { .. } | Error::ClientRunning(_) => io::Error::other(e),
+        }
+    }
+}
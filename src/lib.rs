@@ -0,0 +1,24 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+//! The copy engine behind wow-profile-copy-ng: finding WoW installs and their
+//! characters, and copying configuration and SavedVariables between them.
+//! Nothing in here uses the GUI. Its dependencies are behind the default `gui`
+//! feature, so depend on this crate with `default-features = false` to leave them out.
+
+pub mod addons;
+pub mod backup;
+pub mod bindings;
+pub mod client;
+pub mod config;
+pub mod copy;
+pub mod diff;
+pub mod error;
+pub mod lua;
+pub mod macros;
+pub mod profiles;
+pub mod settings;
+pub mod wow;
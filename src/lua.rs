@@ -0,0 +1,582 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+// reading and writing the subset of lua that the client uses for SavedVariables files:
+//
+//   WeakAurasSaved = {
+//   	["displays"] = {
+//   		...
+//   	},
+//   	"positional", -- [1]
+//   }
+//   PlaterLanguage = nil
+//
+// strings are kept as raw bytes, since addons are free to store anything in them, and numbers as the
+// literal they were read from, so a file that's only read and written comes back byte for byte.
+
+use std::{fs, io::{Error, ErrorKind}, path::Path};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Nil,
+    Boolean(bool),
+    // the literal as written, like `1e-05` or `12345678901234567`, which an f64 can't always hold exactly
+    Number(String),
+    String(Vec<u8>),
+    Table(Table),
+}
+
+// tables keep their entries in file order so they're written back the way they were read
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Table {
+    pub entries: Vec<Entry>,
+}
+
+// `key` is None for positional entries, which the client writes as `value, -- [n]`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Entry {
+    pub key: Option<Value>,
+    pub value: Value,
+}
+
+// every top-level assignment in a SavedVariables file, in order
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct SavedVariables {
+    pub vars: Vec<(String, Value)>,
+}
+
+impl Value {
+    pub fn string(s: &str) -> Value {
+        Value::String(s.as_bytes().to_vec())
+    }
+
+    pub fn number(n: f64) -> Value {
+        Value::Number(format_number(n))
+    }
+
+    pub fn as_number(&self) -> Option<f64> {
+        match self {
+            Value::Number(n) => n.parse().ok(),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::String(s) => std::str::from_utf8(s).ok(),
+            _ => None,
+        }
+    }
+
+    pub fn as_table(&self) -> Option<&Table> {
+        match self {
+            Value::Table(t) => Some(t),
+            _ => None,
+        }
+    }
+
+    pub fn as_table_mut(&mut self) -> Option<&mut Table> {
+        match self {
+            Value::Table(t) => Some(t),
+            _ => None,
+        }
+    }
+}
+
+impl Table {
+    pub fn get(&self, key: &Value) -> Option<&Value> {
+        self.entries
+            .iter()
+            .find(|e| e.key.as_ref() == Some(key))
+            .map(|e| &e.value)
+    }
+
+    pub fn get_mut(&mut self, key: &Value) -> Option<&mut Value> {
+        self.entries
+            .iter_mut()
+            .find(|e| e.key.as_ref() == Some(key))
+            .map(|e| &mut e.value)
+    }
+
+    pub fn get_str(&self, key: &str) -> Option<&Value> {
+        self.get(&Value::string(key))
+    }
+
+    pub fn get_str_mut(&mut self, key: &str) -> Option<&mut Value> {
+        self.get_mut(&Value::string(key))
+    }
+
+    // replaces the value for an existing key, or adds it to the end
+    pub fn insert(&mut self, key: Value, value: Value) {
+        match self.get_mut(&key) {
+            Some(v) => *v = value,
+            None => self.entries.push(Entry { key: Some(key), value }),
+        }
+    }
+}
+
+impl SavedVariables {
+    pub fn parse(input: &[u8]) -> Result<SavedVariables, Error> {
+        let mut parser = Parser { input, pos: 0 };
+        let mut vars: Vec<(String, Value)> = vec![];
+
+        loop {
+            parser.skip_space()?;
+            if parser.peek().is_none() {
+                break
+            }
+            let name = parser.name()?;
+            parser.skip_space()?;
+            parser.expect(b'=')?;
+            let value = parser.value()?;
+            vars.push((name, value));
+            parser.skip_space()?;
+            if parser.peek() == Some(b';') {
+                parser.pos += 1;
+            }
+        }
+
+        Ok(SavedVariables { vars })
+    }
+
+    pub fn read(path: &Path) -> Result<SavedVariables, Error> {
+        SavedVariables::parse(&fs::read(path)?)
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Value> {
+        self.vars.iter().find(|(n, _)| n == name).map(|(_, v)| v)
+    }
+
+    pub fn get_mut(&mut self, name: &str) -> Option<&mut Value> {
+        self.vars.iter_mut().find(|(n, _)| n == name).map(|(_, v)| v)
+    }
+
+    // writes the variables back out in the same layout the client uses
+    pub fn to_bytes(&self) -> Vec<u8> {
+        // the client starts every file with an empty line
+        let mut out: Vec<u8> = vec![b'\n'];
+        for (name, value) in &self.vars {
+            out.extend_from_slice(name.as_bytes());
+            out.extend_from_slice(b" = ");
+            write_value(&mut out, value, 0);
+            out.extend_from_slice(b"\n");
+        }
+        out
+    }
+
+    pub fn write(&self, path: &Path) -> Result<(), Error> {
+        fs::write(path, self.to_bytes())
+    }
+}
+
+fn write_value(out: &mut Vec<u8>, value: &Value, depth: usize) {
+    match value {
+        Value::Nil => out.extend_from_slice(b"nil"),
+        Value::Boolean(b) => out.extend_from_slice(if *b {b"true"} else {b"false"}),
+        Value::Number(n) => out.extend_from_slice(n.as_bytes()),
+        Value::String(s) => write_string(out, s),
+        Value::Table(t) => {
+            out.extend_from_slice(b"{\n");
+            let mut position = 0;
+            for e in &t.entries {
+                out.extend(std::iter::repeat_n(b'\t', depth + 1));
+                match &e.key {
+                    Some(key) => {
+                        out.push(b'[');
+                        write_value(out, key, depth + 1);
+                        out.extend_from_slice(b"] = ");
+                        write_value(out, &e.value, depth + 1);
+                        out.extend_from_slice(b",\n");
+                    },
+                    None => {
+                        position += 1;
+                        write_value(out, &e.value, depth + 1);
+                        out.extend_from_slice(format!(", -- [{}]\n", position).as_bytes());
+                    },
+                }
+            }
+            out.extend(std::iter::repeat_n(b'\t', depth));
+            out.push(b'}');
+        },
+    }
+}
+
+fn write_string(out: &mut Vec<u8>, s: &[u8]) {
+    out.push(b'"');
+    for &c in s {
+        match c {
+            b'\\' => out.extend_from_slice(b"\\\\"),
+            b'"' => out.extend_from_slice(b"\\\""),
+            b'\n' => out.extend_from_slice(b"\\n"),
+            b'\r' => out.extend_from_slice(b"\\r"),
+            // three digits, so a following digit can't be read as part of the escape
+            0..=0x1f | 0x7f => out.extend_from_slice(format!("\\{:03}", c).as_bytes()),
+            _ => out.push(c),
+        }
+    }
+    out.push(b'"');
+}
+
+fn format_number(n: f64) -> String {
+    if n.is_nan() {
+        String::from("0/0")
+    } else if n.is_infinite() {
+        String::from(if n > 0.0 {"1/0"} else {"-1/0"})
+    } else if n.fract() == 0.0 && n.abs() < 1e15 {
+        format!("{}", n as i64)
+    } else {
+        // debug formatting gives the shortest representation that reads back to the same value
+        format!("{:?}", n)
+    }
+}
+
+struct Parser<'a> {
+    input: &'a [u8],
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<u8> {
+        self.input.get(self.pos).copied()
+    }
+
+    fn peek_at(&self, offset: usize) -> Option<u8> {
+        self.input.get(self.pos + offset).copied()
+    }
+
+    fn error(&self, message: &str) -> Error {
+        let line = self.input[..self.pos.min(self.input.len())]
+            .iter()
+            .filter(|&&c| c == b'\n')
+            .count() + 1;
+        Error::new(ErrorKind::InvalidData, format!("line {}: {}", line, message))
+    }
+
+    fn expect(&mut self, c: u8) -> Result<(), Error> {
+        if self.peek() != Some(c) {
+            return Err(self.error(&format!("expected '{}'", c as char)))
+        }
+        self.pos += 1;
+        Ok(())
+    }
+
+    // whitespace and comments
+    fn skip_space(&mut self) -> Result<(), Error> {
+        loop {
+            match self.peek() {
+                Some(c) if c.is_ascii_whitespace() => self.pos += 1,
+                Some(b'-') if self.peek_at(1) == Some(b'-') => {
+                    self.pos += 2;
+                    if self.peek() == Some(b'[') && self.long_bracket_level().is_some() {
+                        self.long_string()?;
+                    } else {
+                        while !matches!(self.peek(), None | Some(b'\n')) {
+                            self.pos += 1;
+                        }
+                    }
+                },
+                _ => return Ok(()),
+            }
+        }
+    }
+
+    fn name(&mut self) -> Result<String, Error> {
+        let start = self.pos;
+        while let Some(c) = self.peek() {
+            if c.is_ascii_alphanumeric() || c == b'_' {
+                self.pos += 1;
+            } else {
+                break
+            }
+        }
+        if start == self.pos || self.input[start].is_ascii_digit() {
+            return Err(self.error("expected a variable name"))
+        }
+        Ok(String::from_utf8_lossy(&self.input[start..self.pos]).into_owned())
+    }
+
+    fn value(&mut self) -> Result<Value, Error> {
+        self.skip_space()?;
+        match self.peek() {
+            Some(b'{') => self.table(),
+            Some(b'"') | Some(b'\'') => Ok(Value::String(self.quoted_string()?)),
+            Some(b'[') if self.long_bracket_level().is_some() => Ok(Value::String(self.long_string()?)),
+            Some(c) if c == b'-' || c == b'.' || c.is_ascii_digit() => self.number(),
+            Some(c) if c.is_ascii_alphabetic() || c == b'_' => match self.name()?.as_str() {
+                "nil" => Ok(Value::Nil),
+                "true" => Ok(Value::Boolean(true)),
+                "false" => Ok(Value::Boolean(false)),
+                other => Err(self.error(&format!("unexpected name '{}'", other))),
+            },
+            Some(_) => Err(self.error("expected a value")),
+            None => Err(self.error("unexpected end of file")),
+        }
+    }
+
+    fn table(&mut self) -> Result<Value, Error> {
+        self.expect(b'{')?;
+        let mut table = Table::default();
+
+        loop {
+            self.skip_space()?;
+            match self.peek() {
+                Some(b'}') => {
+                    self.pos += 1;
+                    return Ok(Value::Table(table))
+                },
+                None => return Err(self.error("unterminated table")),
+                _ => (),
+            }
+
+            let key = if self.peek() == Some(b'[') && self.long_bracket_level().is_none() {
+                self.pos += 1;
+                let key = self.value()?;
+                self.skip_space()?;
+                self.expect(b']')?;
+                self.skip_space()?;
+                self.expect(b'=')?;
+                Some(key)
+            } else if self.peek().is_some_and(|c| c.is_ascii_alphabetic() || c == b'_') && self.is_name_key() {
+                let key = Value::string(&self.name()?);
+                self.skip_space()?;
+                self.expect(b'=')?;
+                Some(key)
+            } else {
+                None
+            };
+
+            let value = self.value()?;
+            table.entries.push(Entry { key, value });
+
+            self.skip_space()?;
+            match self.peek() {
+                Some(b',') | Some(b';') => self.pos += 1,
+                Some(b'}') => (),
+                _ => return Err(self.error("expected ',' or '}'")),
+            }
+        }
+    }
+
+    // `name = value` as opposed to a positional `true`, `nil`, etc.
+    fn is_name_key(&self) -> bool {
+        let mut i = self.pos;
+        while i < self.input.len() && (self.input[i].is_ascii_alphanumeric() || self.input[i] == b'_') {
+            i += 1;
+        }
+        while i < self.input.len() && self.input[i].is_ascii_whitespace() {
+            i += 1;
+        }
+        self.input.get(i) == Some(&b'=') && self.input.get(i + 1) != Some(&b'=')
+    }
+
+    fn number(&mut self) -> Result<Value, Error> {
+        let start = self.pos;
+        if self.peek() == Some(b'-') {
+            self.pos += 1;
+            self.skip_space()?;
+        }
+        let digits = self.pos;
+        while let Some(c) = self.peek() {
+            let exponent_sign = (c == b'-' || c == b'+')
+                && matches!(self.input.get(self.pos - 1), Some(b'e') | Some(b'E'));
+            if c.is_ascii_digit() || c == b'.' || c == b'e' || c == b'E' || exponent_sign {
+                self.pos += 1;
+            } else {
+                break
+            }
+        }
+
+        let text = std::str::from_utf8(&self.input[digits..self.pos]).unwrap_or_default();
+        if text.parse::<f64>().is_err() {
+            return Err(self.error(&format!("invalid number '{}'", text)))
+        }
+        Ok(Value::Number(if self.input[start] == b'-' { format!("-{}", text) } else { text.to_owned() }))
+    }
+
+    fn quoted_string(&mut self) -> Result<Vec<u8>, Error> {
+        let quote = self.peek().unwrap();
+        self.pos += 1;
+        let mut s: Vec<u8> = vec![];
+
+        loop {
+            let c = match self.peek() {
+                Some(c) => c,
+                None => return Err(self.error("unterminated string")),
+            };
+            self.pos += 1;
+
+            if c == quote {
+                return Ok(s)
+            }
+            if c != b'\\' {
+                s.push(c);
+                continue
+            }
+
+            let escaped = match self.peek() {
+                Some(e) => e,
+                None => return Err(self.error("unterminated string")),
+            };
+            self.pos += 1;
+            match escaped {
+                b'a' => s.push(0x07),
+                b'b' => s.push(0x08),
+                b'f' => s.push(0x0c),
+                b'n' => s.push(b'\n'),
+                b'r' => s.push(b'\r'),
+                b't' => s.push(b'\t'),
+                b'v' => s.push(0x0b),
+                b'\n' => s.push(b'\n'),
+                b'0'..=b'9' => {
+                    let mut code: u32 = (escaped - b'0') as u32;
+                    for _ in 0..2 {
+                        match self.peek() {
+                            Some(d) if d.is_ascii_digit() => {
+                                code = code * 10 + (d - b'0') as u32;
+                                self.pos += 1;
+                            },
+                            _ => break,
+                        }
+                    }
+                    if code > 255 {
+                        return Err(self.error("escape sequence too large"))
+                    }
+                    s.push(code as u8);
+                },
+                other => s.push(other),
+            }
+        }
+    }
+
+    // the level of a [[ or [==[ opening bracket at the current position
+    fn long_bracket_level(&self) -> Option<usize> {
+        let mut level = 0;
+        while self.peek_at(1 + level) == Some(b'=') {
+            level += 1;
+        }
+        match self.peek_at(1 + level) {
+            Some(b'[') => Some(level),
+            _ => None,
+        }
+    }
+
+    fn long_string(&mut self) -> Result<Vec<u8>, Error> {
+        let level = self.long_bracket_level().unwrap();
+        self.pos += level + 2;
+        // a newline straight after the opening bracket isn't part of the string
+        if self.peek() == Some(b'\n') {
+            self.pos += 1;
+        }
+
+        let mut close = vec![b']'];
+        close.extend(std::iter::repeat_n(b'=', level));
+        close.push(b']');
+
+        let start = self.pos;
+        while self.pos + close.len() <= self.input.len() {
+            if self.input[self.pos..].starts_with(&close) {
+                let s = self.input[start..self.pos].to_vec();
+                self.pos += close.len();
+                return Ok(s)
+            }
+            self.pos += 1;
+        }
+        Err(self.error("unterminated long string"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // a file the way the client writes it comes back byte for byte
+    #[test]
+    fn round_trip() {
+        let file = b"
+WeakAurasSaved = {
+\t[\"dynamicIconCache\"] = {
+\t},
+\t[\"displays\"] = {
+\t\t[\"Rune Tracker\"] = {
+\t\t\t[\"alpha\"] = 1,
+\t\t\t[\"xOffset\"] = -12.5,
+\t\t\t[\"scale\"] = 1e-05,
+\t\t\t[\"internalVersion\"] = 78,
+\t\t\t[\"lastUpdate\"] = 12345678901234567,
+\t\t\t[\"desc\"] = \"say \\\"hi\\\"\\n\\\\ then\\001stop\",
+\t\t\t[\"load\"] = {
+\t\t\t\t[\"use_never\"] = false,
+\t\t\t\t[\"class\"] = {
+\t\t\t\t\t[\"multi\"] = {
+\t\t\t\t\t},
+\t\t\t\t},
+\t\t\t},
+\t\t\t[\"triggers\"] = {
+\t\t\t\t{
+\t\t\t\t\t[\"trigger\"] = {
+\t\t\t\t\t\t[\"type\"] = \"aura2\",
+\t\t\t\t\t},
+\t\t\t\t}, -- [1]
+\t\t\t\t\"positional\", -- [2]
+\t\t\t\tnil, -- [3]
+\t\t\t\ttrue, -- [4]
+\t\t\t},
+\t\t},
+\t},
+\t[1] = 2.5e+20,
+}
+PlaterLanguage = nil
+";
+        let vars = SavedVariables::parse(file).unwrap();
+        assert_eq!(String::from_utf8_lossy(&vars.to_bytes()), String::from_utf8_lossy(file));
+    }
+
+    #[test]
+    fn numbers_keep_their_literal() {
+        let vars = SavedVariables::parse(b"A = {1e-05, 12345678901234567, - 3}").unwrap();
+        let a = vars.get("A").unwrap().as_table().unwrap();
+        assert_eq!(a.entries[0].value, Value::Number(String::from("1e-05")));
+        assert_eq!(a.entries[0].value.as_number(), Some(1e-05));
+        assert_eq!(a.entries[1].value, Value::Number(String::from("12345678901234567")));
+        assert_eq!(a.entries[2].value.as_number(), Some(-3.0));
+        assert_eq!(Value::number(42.0), Value::Number(String::from("42")));
+    }
+
+    #[test]
+    fn escapes() {
+        let vars = SavedVariables::parse(b"A = \"tab\\tbell\\a\\65\\0659\\\nline\"\nB = 'single \"quoted\"'").unwrap();
+        assert_eq!(vars.get("A"), Some(&Value::String(b"tab\tbell\x07AA9\nline".to_vec())));
+        assert_eq!(vars.get("B"), Some(&Value::string("single \"quoted\"")));
+
+        // control characters are written as three digit escapes so they read back the same
+        let written = SavedVariables { vars: vec![(String::from("C"), Value::String(b"\x01\x7f2\r".to_vec()))] }.to_bytes();
+        assert_eq!(written, b"\nC = \"\\001\\1272\\r\"\n");
+        assert_eq!(SavedVariables::parse(&written).unwrap().get("C"), Some(&Value::String(b"\x01\x7f2\r".to_vec())));
+    }
+
+    #[test]
+    fn long_strings_and_comments() {
+        let file = b"
+--[==[ a long comment
+with ]] inside ]==]
+A = [[
+first line
+second]] -- a line comment
+B = {
+\t[ [==[key]=]still]==] ] = [=[x]]y]=], --[[ between ]] 2,
+}
+";
+        let vars = SavedVariables::parse(file).unwrap();
+        assert_eq!(vars.get("A"), Some(&Value::string("first line\nsecond")));
+        let b = vars.get("B").unwrap().as_table().unwrap();
+        assert_eq!(b.get_str("key]=]still"), Some(&Value::string("x]]y")));
+        assert_eq!(b.entries[1], Entry { key: None, value: Value::Number(String::from("2")) });
+
+        // long strings are written back quoted, with the same contents
+        let again = SavedVariables::parse(&vars.to_bytes()).unwrap();
+        assert_eq!(again, vars);
+    }
+}
@@ -0,0 +1,262 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+// macros-cache.txt files, which hold a character's or an account's macros:
+//
+//   VER 3 0000000000000001 "Pyroblast" "INV_MISC_QUESTIONMARK"
+//   #showtooltip
+//   /cast Pyroblast
+//   END
+//
+// older clients write `MACRO 1 "Pyroblast" Spell_Fire_Fireball02` as the header instead.
+// the client only has so many macro slots, and drops whatever doesn't fit.
+
+use crate::addons::Flavor;
+use std::{collections::BTreeSet, fs, io::{Error, ErrorKind}, path::Path};
+
+// slots for each character's own macros
+pub const CHARACTER_MACRO_SLOTS: usize = 18;
+
+// slots for general (account-wide) macros. Classic Era still has vanilla's 36.
+pub fn account_macro_slots(flavor: Flavor) -> usize {
+    match flavor {
+        Flavor::Vanilla => 36,
+        Flavor::Classic | Flavor::Mainline => 120,
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Macro {
+    // everything in the header before the id, "VER 3" or "MACRO"
+    pub prefix: String,
+    pub id: String,
+    pub name: String,
+    // kept as written, quotes and all
+    pub icon: String,
+    pub body: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct MacrosCache {
+    pub macros: Vec<Macro>,
+    crlf: bool,
+}
+
+// what a merge did, by macro name
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct MergeReport {
+    pub replaced: Vec<String>,
+    pub added: Vec<String>,
+    // macros that didn't fit in the destination's free slots and were left out
+    pub overflow: Vec<String>,
+}
+
+impl Macro {
+    // the first line of the body that isn't a #showtooltip style directive, for showing in a list
+    pub fn summary(&self) -> &str {
+        self.body
+            .iter()
+            .find(|l| !l.starts_with('#') && !l.trim().is_empty())
+            .or(self.body.first())
+            .map(|l| l.as_str())
+            .unwrap_or_default()
+    }
+}
+
+impl MacrosCache {
+    pub fn parse(input: &str) -> Result<MacrosCache, Error> {
+        let mut macros: Vec<Macro> = vec![];
+        let mut lines = input.lines().enumerate();
+
+        while let Some((n, line)) = lines.next() {
+            if line.trim().is_empty() {
+                continue
+            }
+            let mut m = parse_header(line).ok_or_else(|| invalid(n, "expected a macro header"))?;
+            loop {
+                match lines.next() {
+                    Some((_, "END")) => break,
+                    Some((_, body)) => m.body.push(body.to_owned()),
+                    None => return Err(invalid(n, &format!("macro {:?} has no END", m.name))),
+                }
+            }
+            macros.push(m);
+        }
+
+        Ok(MacrosCache { macros, crlf: input.contains("\r\n") })
+    }
+
+    pub fn read(path: &Path) -> Result<MacrosCache, Error> {
+        MacrosCache::parse(&String::from_utf8_lossy(&fs::read(path)?))
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Macro> {
+        self.macros.iter().find(|m| m.name == name)
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let newline = if self.crlf {"\r\n"} else {"\n"};
+        let mut out = String::new();
+        for m in &self.macros {
+            out += &format!("{} {} \"{}\" {}{}", m.prefix, m.id, m.name, m.icon, newline);
+            for line in &m.body {
+                out += line;
+                out += newline;
+            }
+            out += "END";
+            out += newline;
+        }
+        out.into_bytes()
+    }
+
+    // an id that isn't used yet, written the same way as the ones already in the file
+    fn next_id(&self, like: &Macro) -> String {
+        let next = self.macros
+            .iter()
+            .filter_map(|m| u64::from_str_radix(&m.id, if m.prefix == "MACRO" {10} else {16}).ok())
+            .max()
+            .map_or(1, |id| id + 1);
+
+        match like.prefix.as_str() {
+            "MACRO" => next.to_string(),
+            _ => format!("{:0width$X}", next, width = like.id.len()),
+        }
+    }
+}
+
+// copies the named macros from src into dst. a macro with the same name in dst is replaced,
+// anything else is added at the end while there are slots left. names don't have to be unique, so
+// the second macro with a name replaces the second one in dst with that name, and so on.
+pub fn merge(dst: &MacrosCache, src: &MacrosCache, names: &BTreeSet<String>, slots: usize) -> (MacrosCache, MergeReport) {
+    let mut merged = dst.clone();
+    let mut report = MergeReport::default();
+
+    for (i, m) in src.macros.iter().enumerate().filter(|(_, m)| names.contains(&m.name)) {
+        let nth = src.macros[..i].iter().filter(|s| s.name == m.name).count();
+        // only dst's own macros, not ones added by this merge
+        if let Some(existing) = merged.macros[..dst.macros.len()].iter_mut().filter(|d| d.name == m.name).nth(nth) {
+            existing.icon = m.icon.clone();
+            existing.body = m.body.clone();
+            report.replaced.push(m.name.clone());
+        } else if merged.macros.len() < slots {
+            let id = merged.next_id(m);
+            merged.macros.push(Macro { id, ..m.clone() });
+            report.added.push(m.name.clone());
+        } else {
+            report.overflow.push(m.name.clone());
+        }
+    }
+
+    (merged, report)
+}
+
+// `VER 3 <id> "name" icon` or `MACRO <id> "name" icon`
+fn parse_header(line: &str) -> Option<Macro> {
+    let (prefix, rest) = if let Some(rest) = line.strip_prefix("VER ") {
+        let (ver, rest) = rest.split_once(' ')?;
+        (format!("VER {}", ver), rest)
+    } else {
+        (String::from("MACRO"), line.strip_prefix("MACRO ")?)
+    };
+    let (id, rest) = rest.split_once(' ')?;
+    let rest = rest.strip_prefix('"')?;
+    let (name, icon) = rest.split_once('"')?;
+
+    Some(Macro {
+        prefix,
+        id: id.to_owned(),
+        name: name.to_owned(),
+        icon: icon.trim().to_owned(),
+        body: vec![],
+    })
+}
+
+fn invalid(line: usize, message: &str) -> Error {
+    Error::new(ErrorKind::InvalidData, format!("line {}: {}", line + 1, message))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MAIN: &str = "VER 3 0000000000000001 \"Pyroblast\" \"INV_MISC_QUESTIONMARK\"\r\n#showtooltip\r\n/cast Pyroblast\r\nEND\r\nVER 3 000000000000000A \"Heal, Target\" \"134400\"\r\n/cast [@mouseover,help][] Flash Heal\r\n/target focus\r\nEND\r\n";
+    const ALT: &str = "VER 3 0000000000000002 \"Pyroblast\" \"INV_MISC_QUESTIONMARK\"\r\n/cast Fireball\r\nEND\r\n";
+
+    #[test]
+    fn parse_and_round_trip() {
+        let cache = MacrosCache::parse(MAIN).unwrap();
+        assert_eq!(cache.macros.len(), 2);
+        assert_eq!(cache.macros[1], Macro {
+            prefix: String::from("VER 3"),
+            id: String::from("000000000000000A"),
+            name: String::from("Heal, Target"),
+            icon: String::from("\"134400\""),
+            body: vec![String::from("/cast [@mouseover,help][] Flash Heal"), String::from("/target focus")],
+        });
+        assert_eq!(cache.macros[0].summary(), "/cast Pyroblast");
+        assert_eq!(cache.to_bytes(), MAIN.as_bytes());
+
+        // older clients
+        let old = "MACRO 1 \"Mount\" Ability_Mount_RidingHorse\n/script CallCompanion(\"MOUNT\", 1)\nEND\n";
+        let cache = MacrosCache::parse(old).unwrap();
+        assert_eq!(cache.macros[0].prefix, "MACRO");
+        assert_eq!(cache.macros[0].icon, "Ability_Mount_RidingHorse");
+        assert_eq!(cache.to_bytes(), old.as_bytes());
+    }
+
+    #[test]
+    fn parse_errors() {
+        let e = MacrosCache::parse("VER 3 0000000000000001 \"Pyroblast\" \"INV_MISC_QUESTIONMARK\"\n/cast Pyroblast\n").unwrap_err();
+        assert_eq!(e.to_string(), "line 1: macro \"Pyroblast\" has no END");
+        let e = MacrosCache::parse("END\n").unwrap_err();
+        assert_eq!(e.to_string(), "line 1: expected a macro header");
+    }
+
+    #[test]
+    fn next_id_follows_the_file() {
+        let cache = MacrosCache::parse(MAIN).unwrap();
+        assert_eq!(cache.next_id(&cache.macros[0]), "000000000000000B");
+
+        let old = MacrosCache::parse("MACRO 9 \"Mount\" Ability_Mount_RidingHorse\nEND\n").unwrap();
+        assert_eq!(old.next_id(&old.macros[0]), "10");
+        assert_eq!(MacrosCache::default().next_id(&old.macros[0]), "1");
+    }
+
+    #[test]
+    fn merge_replaces_adds_and_overflows() {
+        let names = BTreeSet::from([String::from("Pyroblast"), String::from("Heal, Target"), String::from("Missing")]);
+        let (merged, report) = merge(&MacrosCache::parse(ALT).unwrap(), &MacrosCache::parse(MAIN).unwrap(), &names, 2);
+        assert_eq!(report, MergeReport { replaced: vec![String::from("Pyroblast")], added: vec![String::from("Heal, Target")], overflow: vec![] });
+        // the replaced macro keeps the target's id, the added one gets the next free one
+        assert_eq!(String::from_utf8(merged.to_bytes()).unwrap(), "VER 3 0000000000000002 \"Pyroblast\" \"INV_MISC_QUESTIONMARK\"\r\n#showtooltip\r\n/cast Pyroblast\r\nEND\r\nVER 3 0000000000000003 \"Heal, Target\" \"134400\"\r\n/cast [@mouseover,help][] Flash Heal\r\n/target focus\r\nEND\r\n");
+
+        let (merged, report) = merge(&MacrosCache::parse(ALT).unwrap(), &MacrosCache::parse(MAIN).unwrap(), &names, 1);
+        assert_eq!(report.overflow, vec![String::from("Heal, Target")]);
+        assert_eq!(merged.macros.len(), 1);
+    }
+
+    #[test]
+    fn merge_duplicate_names() {
+        let dst = MacrosCache::parse("VER 3 0000000000000001 \"Heal\" \"134400\"\n/cast Heal\nEND\nVER 3 0000000000000002 \"Other\" \"134400\"\nEND\n").unwrap();
+        let src = MacrosCache::parse("VER 3 0000000000000001 \"Heal\" \"134400\"\n/cast Flash Heal\nEND\nVER 3 0000000000000002 \"Heal\" \"134401\"\n/cast Renew\nEND\n").unwrap();
+        let (merged, report) = merge(&dst, &src, &BTreeSet::from([String::from("Heal")]), 120);
+        // the first replaces the target's only "Heal", the second is added rather than replacing it again
+        assert_eq!(report, MergeReport { replaced: vec![String::from("Heal")], added: vec![String::from("Heal")], overflow: vec![] });
+        assert_eq!(String::from_utf8(merged.to_bytes()).unwrap(), "VER 3 0000000000000001 \"Heal\" \"134400\"\n/cast Flash Heal\nEND\nVER 3 0000000000000002 \"Other\" \"134400\"\nEND\nVER 3 0000000000000003 \"Heal\" \"134401\"\n/cast Renew\nEND\n");
+
+        // merging the same macros again replaces both instead of adding more
+        let (again, report) = merge(&merged, &src, &BTreeSet::from([String::from("Heal")]), 120);
+        assert_eq!(report.replaced.len(), 2);
+        assert_eq!(again, merged);
+    }
+
+    #[test]
+    fn classic_era_has_fewer_general_slots() {
+        assert_eq!(account_macro_slots(Flavor::of(std::ffi::OsStr::new("_classic_era_"))), 36);
+        assert_eq!(account_macro_slots(Flavor::of(std::ffi::OsStr::new("_classic_"))), 120);
+        assert_eq!(account_macro_slots(Flavor::of(std::ffi::OsStr::new("_retail_"))), 120);
+    }
+}
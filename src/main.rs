@@ -4,14 +4,15 @@
  * file, You can obtain one at https://mozilla.org/MPL/2.0/.
  */
 
-use std::borrow::Cow;
+use std::{borrow::Cow, env, process::ExitCode};
 
-use iced::{font, Font};
+use iced::{font, Font, Size, Task};
+use wow_profile_copy_ng::settings::Settings;
 
 use crate::operation::Operation;
 
+mod cli;
 mod operation;
-mod wow;
 
 fn fonts() -> Vec<Cow<'static, [u8]>> {
     vec![
@@ -22,7 +23,12 @@ fn fonts() -> Vec<Cow<'static, [u8]>> {
         ]
 }
 
-fn main() -> iced::Result {
+fn main() -> ExitCode {
+    let args: Vec<String> = env::args().skip(1).collect();
+    if args.first().is_some_and(|arg| cli::is_command(arg)) {
+        return cli::run(args)
+    }
+
     let settings = iced::Settings {
         id: Some(String::from("wow-profile-copy-ng")),
         fonts: fonts(),
@@ -35,8 +41,26 @@ fn main() -> iced::Result {
         default_text_size: iced::Pixels(16.0),
         antialiasing: true
     };
-    iced::application("wow-profile-copy-ng", Operation::update, Operation::view)
+    let saved = Settings::load().unwrap_or_else(|e| {
+        eprintln!("error reading settings, using the defaults: {}", e);
+        Settings::default()
+    });
+    let mut app = iced::application("wow-profile-copy-ng", Operation::update, Operation::view)
     .settings(settings)
     .theme(Operation::theme)
-    .run()
+    .subscription(Operation::subscription)
+    // the window's size is saved before it closes
+    .exit_on_close_request(false);
+    if let Some((width, height)) = saved.window {
+        app = app.window_size(Size::new(width as f32, height as f32));
+    }
+    let result = app.run_with(move || (Operation::new(saved), Task::none()));
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("{}", e);
+            ExitCode::FAILURE
+        }
+    }
 }
\ No newline at end of file
@@ -4,121 +4,357 @@
  * file, You can obtain one at https://mozilla.org/MPL/2.0/.
  */
 
-use crate::wow::{self, Install, Version, Wtf};
-use iced::{alignment, border, font, Element, Fill, FillPortion, Font, Theme};
-use iced::widget::{button, checkbox, column, container, horizontal_rule, row, scrollable, text, Container};
-use std::{env, path::PathBuf, ffi::OsString, fs, io::Error};
-use dark_light;
+use iced::{alignment, border, font, stream, window, Color, Element, Fill, FillPortion, Font, Size, Subscription, Task, Theme};
+use iced::futures::{executor::block_on, SinkExt};
+use iced::widget::{button, center, checkbox, column, container, horizontal_rule, opaque, pick_list, progress_bar, row, scrollable, stack, text, text_input, Column, Container};
+use rfd::FileDialog;
+use wow_profile_copy_ng::addons::{self, AddonFolder, AddonStatus, Flavor};
+use wow_profile_copy_ng::backup::{self, Snapshot};
+use wow_profile_copy_ng::client;
+use std::{collections::{BTreeMap, BTreeSet}, ffi::OsString, fs, path::{Path, PathBuf}, sync::{atomic::{AtomicBool, Ordering}, Arc}, thread};
+use wow_profile_copy_ng::bindings::{self, BindingsCache};
+use wow_profile_copy_ng::config::{self, Category, ConfigCache};
+use wow_profile_copy_ng::copy::{self, Action, CopyPlan, CopyReport, CopyRequest, Counts, PlanEntry, Progress, SavedVariablesFile, Scope, Status};
+use wow_profile_copy_ng::error::Error;
+use wow_profile_copy_ng::macros::MacrosCache;
+use wow_profile_copy_ng::settings::{self, CopyOptions, Preset, Selection, Settings, ThemeChoice};
+use wow_profile_copy_ng::diff::{self, DiffRow, FileDiff, RowKind};
+use wow_profile_copy_ng::wow::{self, Install, Version, Wtf};
 
 
 // todo: change to Option<&T>
 #[derive(Debug, Clone)]
 pub struct Operation {
-    install: Option<Install>,
+    // every install that was found or added, offered on the start screen and in each column
+    installs: Vec<Install>,
+    // the installs being copied from and to, which are the same one unless picked otherwise
+    src_install: Option<Install>,
+    dst_install: Option<Install>,
     src_ver: Option<Version>,
     src_wtf: Option<Wtf>,
     dst_ver: Option<Version>,
-    dst_wtf: Option<Wtf>,
+    // every character being copied to, and whether the user is done picking them
+    dst_wtfs: Vec<Wtf>,
+    dst_picked: bool,
     copy_logs: Option<Vec<String>>,
+    // what the last copy did, shown in the Logs pane when there's nothing newer to show
+    report: Option<Vec<CopyReport>>,
+    // Some while a copy is waiting for confirmation, one plan per destination
+    plan: Option<Vec<CopyPlan>>,
+    // Some while a copy is running in the background, and the flag that stops it
+    progress: Option<Progress>,
+    cancel: Arc<AtomicBool>,
+    // Some while the snapshot restore view is open
+    snapshots: Option<Vec<Snapshot>>,
+    // Some while comparing a planned file with the destination
+    diff: Option<FileDiff>,
+    // every addon with SavedVariables for the source character
+    savedvars: Vec<SavedVariablesFile>,
+    // addons the user unticked. kept across selections so the choice sticks.
+    skip_savedvars: BTreeSet<SavedVariablesFile>,
     overwrite_account: bool,
+    // the source character's config-cache.wtf files
+    src_cvars: Vec<(Scope, ConfigCache)>,
+    // CVars picked for merging at each scope. scopes without an entry have the whole file copied.
+    merge_cvars: BTreeMap<Scope, BTreeSet<String>>,
+    // CVars the target always keeps its own values for, and the box for adding to them
+    keep_cvars: BTreeSet<String>,
+    keep_input: String,
+    cvars_open: bool,
+    // the key bindings the source uses and whether they're its own or the account's,
+    // and the target's if there's only one
+    src_bindings: Option<(Scope, BindingsCache)>,
+    dst_bindings: Option<(Scope, BindingsCache)>,
+    // keys picked for merging, None to copy the whole file
+    merge_bindings: Option<BTreeSet<String>>,
+    bindings_search: String,
+    bindings_open: bool,
+    // the source's macros at each scope, and the ones picked for merging by name
+    src_macros: Vec<(Scope, MacrosCache)>,
+    merge_macros: BTreeMap<Scope, BTreeSet<String>>,
+    macros_open: bool,
+    // Some while picking addon folders to copy, with how each compares to the target version
+    addon_folders: Option<Vec<AddonFolder>>,
+    sync_addons: BTreeSet<String>,
+    // saved copies, whether they're being managed, and the name box for saving a new one
+    presets: Vec<Preset>,
+    presets_open: bool,
+    preset_name: String,
+    // Some while an error is shown over everything else
+    dialog: Option<Dialog>,
+    theme: ThemeChoice,
+    window: Option<(u32, u32)>,
+    // what was last written to the settings file, so it's only written again when something changes
+    saved: Settings,
+}
+
+// an error explained in a box over the window, with buttons for what can be done about it
+#[derive(Debug, Clone)]
+pub struct Dialog {
+    title: String,
+    body: String,
+    // next steps besides closing it
+    actions: Vec<(&'static str, Message)>,
 }
 
 #[derive(Debug, Clone)]
 pub enum Message {
     Install,
+    UseInstall(Install),
+    PickInstall(Install, bool),
     Version(Version, bool),
     Wtf(Wtf, bool),
+    TargetToggle(Wtf, bool),
+    TargetAll(Vec<Wtf>, bool),
+    TargetPicked,
+    Preview,
     Copy,
+    // copies without checking for running clients first
+    CopyAnyway,
+    CopyProgress(Progress),
+    // the reports, or why the copy couldn't start
+    CopyDone(Result<Vec<CopyReport>, Dialog>),
+    ExportReport,
+    StopCopy,
+    Cancel,
     Reset(bool),
     OverwriteToggle(bool),
+    Snapshots(bool),
+    Diff(Option<PlanEntry>),
+    Restore(Snapshot),
+    DeleteSnapshot(Snapshot),
+    SavedVariablesToggle(SavedVariablesFile, bool),
+    SavedVariablesAll(bool),
+    Cvars(bool),
+    CvarMode(Scope, bool),
+    CvarToggle(Scope, String, bool),
+    CvarCategory(Scope, Category, bool),
+    KeepInput(String),
+    KeepAdd,
+    KeepRemove(String),
+    KeepDefaults,
+    Bindings(bool),
+    BindingsMode(bool),
+    BindingToggle(String, bool),
+    BindingsShown(bool),
+    BindingsSearch(String),
+    Macros(bool),
+    MacroMode(Scope, bool),
+    MacroToggle(Scope, String, bool),
+    AddonFolders(bool),
+    AddonFolderToggle(String, bool),
+    Presets(bool),
+    PresetName(String),
+    PresetSave,
+    PresetLoad(Preset),
+    PresetRun(Preset),
+    PresetDelete(String),
+    Theme(ThemeChoice),
+    Resized(Size),
+    // the window is closing, which is when its size is saved
+    CloseRequested(window::Id),
+    // closes the dialog, then takes the next step picked in it
+    DismissDialog(Option<Box<Message>>),
 }
 
+impl Message {
+    // whether handling it can change anything that's saved between runs. resizing sends a stream of
+    // messages, so the size waits for the window to close.
+    fn changes_settings(&self) -> bool {
+        !matches!(self, Message::Preview | Message::Copy | Message::CopyAnyway | Message::CopyProgress(_) | Message::CopyDone(_) | Message::ExportReport | Message::StopCopy | Message::Cancel | Message::Snapshots(_) | Message::Diff(_) | Message::Restore(_) | Message::DeleteSnapshot(_) | Message::Cvars(_) | Message::KeepInput(_) | Message::Bindings(_) | Message::BindingsSearch(_) | Message::Macros(_) | Message::AddonFolders(_) | Message::Presets(_) | Message::PresetName(_) | Message::PresetSave | Message::PresetDelete(_) | Message::Resized(_) | Message::CloseRequested(_) | Message::DismissDialog(_))
+    }
+}
 
-impl std::default::Default for Operation {
-    fn default() -> Self {
-        let folder: OsString;
-        if cfg!(target_os = "windows") {
-            folder = OsString::from("C:\\Program Files (x86)\\World of Warcraft");
-        } else if cfg!(target_os = "macos") {
-            folder = OsString::from("/Applications/World of Warcraft");
-        } else if cfg!(target_os = "linux") {
-            let home = env::var_os("HOME");
-            if home.is_none() {
-                return Operation {
-                    install: None,
-                    src_ver: None,
-                    dst_ver: None,
-                    src_wtf: None,
-                    dst_wtf: None,
-                    copy_logs: None,
-                    overwrite_account: true
-                }
-            }
-            folder = PathBuf::from(home.unwrap())
-                .join("Games/battlenet/drive_c/Program Files (x86)/World of Warcraft")
-                .into_os_string();
-        } else {
-            return Operation {
-                install: None,
-                src_ver: None,
-                dst_ver: None,
-                src_wtf: None,
-                dst_wtf: None,
-                copy_logs: None,
-                overwrite_account: true
+impl Operation {
+    // picks up where the last run left off
+    pub fn new(saved: Settings) -> Self {
+        let mut installs = wow::find_installs();
+        for dir in &saved.installs {
+            if installs.iter().any(|i| &i.install_dir == dir) {
+                continue
+            }
+            // ones that have since been moved or uninstalled are forgotten
+            if let Ok(install) = wow::get_wow_install(dir.clone()) {
+                installs.push(install);
             }
         }
 
-        match wow::get_wow_install(folder) {
-            Ok(install) => {
-                Operation {
-                    install: Some(install),
-                    src_ver: None,
-                    dst_ver: None,
-                    src_wtf: None,
-                    dst_wtf: None,
-                    copy_logs: None,
-                    overwrite_account: true
-                }
-            }
-            Err(_) => {
-                Operation {
-                    install: None,
-                    src_ver: None,
-                    dst_ver: None,
-                    src_wtf: None,
-                    dst_wtf: None,
-                    copy_logs: None,
-                    overwrite_account: true
+        let mut op = Operation {
+            installs,
+            src_install: None,
+            dst_install: None,
+            src_ver: None,
+            dst_ver: None,
+            src_wtf: None,
+            dst_wtfs: vec![],
+            dst_picked: false,
+            copy_logs: None,
+            report: None,
+            plan: None,
+            progress: None,
+            cancel: Arc::new(AtomicBool::new(false)),
+            snapshots: None,
+            diff: None,
+            savedvars: vec![],
+            skip_savedvars: saved.options.skip_savedvars.clone(),
+            overwrite_account: saved.options.overwrite_account,
+            src_cvars: vec![],
+            merge_cvars: saved.options.merge_cvars.clone(),
+            keep_cvars: saved.options.keep_cvars.clone(),
+            keep_input: String::new(),
+            cvars_open: false,
+            src_bindings: None,
+            dst_bindings: None,
+            merge_bindings: saved.options.merge_bindings.clone(),
+            bindings_search: String::new(),
+            bindings_open: false,
+            src_macros: vec![],
+            merge_macros: saved.options.merge_macros.clone(),
+            macros_open: false,
+            addon_folders: None,
+            sync_addons: saved.options.sync_addons.clone(),
+            presets: vec![],
+            presets_open: false,
+            preset_name: String::new(),
+            dialog: None,
+            theme: saved.theme,
+            window: saved.window,
+            saved: saved.clone(),
+        };
+
+        match Preset::load_all() {
+            Ok(p) => op.presets = p,
+            Err(e) => op.copy_logs = Some(vec![format!("error reading presets: {}", e)]),
+        }
+
+        op.restore(&saved.selection);
+        // no need to ask if there's only the one
+        if let (None, [install]) = (&op.src_install, op.installs.as_slice()) {
+            op.set_install(install.clone());
+        }
+        op
+    }
+
+    pub fn update(&mut self, message: Message) -> Task<Message> {
+        let saves = message.changes_settings();
+        let task = match message {
+            Message::Copy => self.start_copy(true),
+            Message::CopyAnyway => self.start_copy(false),
+            Message::CloseRequested(id) => {
+                self.save_settings();
+                return window::close(id)
+            },
+            // the next step can be a copy, so it's taken here rather than in handle
+            Message::DismissDialog(next) => {
+                self.dialog = None;
+                match next {
+                    Some(next) => self.update(*next),
+                    None => Task::none(),
                 }
+            },
+            message => {
+                self.handle(message);
+                Task::none()
+            },
+        };
+
+        if saves {
+            self.save_settings();
+        }
+        task
+    }
+
+    pub fn subscription(&self) -> Subscription<Message> {
+        Subscription::batch([
+            window::resize_events().map(|(_, size)| Message::Resized(size)),
+            window::close_requests().map(Message::CloseRequested),
+        ])
+    }
+
+    fn save_settings(&mut self) {
+        let settings = self.settings();
+        if settings != self.saved {
+            if let Err(e) = settings.save() {
+                self.copy_logs = Some(vec![format!("error saving settings: {}", e)]);
             }
+            self.saved = settings;
         }
     }
-}
 
-impl Operation {
-    pub fn update(&mut self, message: Message) {
+    fn handle(&mut self, message: Message) {
+        // anything that changes the selection makes a pending plan stale
+        if !matches!(message, Message::CopyProgress(_) | Message::CopyDone(_) | Message::ExportReport | Message::StopCopy | Message::Snapshots(_) | Message::DeleteSnapshot(_) | Message::Diff(_) | Message::Cvars(_) | Message::KeepInput(_) | Message::Bindings(_) | Message::BindingsSearch(_) | Message::Macros(_) | Message::AddonFolders(_) | Message::Presets(_) | Message::PresetName(_) | Message::PresetSave | Message::PresetDelete(_) | Message::Theme(_) | Message::Resized(_) | Message::DismissDialog(_)) {
+            self.plan = None;
+        }
+
         match message {
             Message::OverwriteToggle(o) => self.overwrite_account = o,
+            Message::Presets(open) => self.presets_open = open,
+            Message::PresetName(name) => self.preset_name = name,
+            Message::PresetSave => {
+                let name = self.preset_name.trim().to_owned();
+                if name.is_empty() || self.requests().is_none() {
+                    return
+                }
+                let preset = Preset { name, selection: self.selection(), options: self.options() };
+                // saving under a name that's taken replaces it
+                match self.presets.iter_mut().find(|p| p.name == preset.name) {
+                    Some(existing) => *existing = preset,
+                    None => self.presets.push(preset),
+                }
+                self.preset_name.clear();
+                if let Err(e) = Preset::save_all(&self.presets) {
+                    self.copy_logs = Some(vec![format!("error saving presets: {}", e)]);
+                }
+            },
+            Message::PresetLoad(preset) => {
+                self.load_preset(&preset);
+            },
+            // the plan comes up like any other, so nothing changes until it's confirmed
+            Message::PresetRun(preset) => {
+                if self.load_preset(&preset) {
+                    self.handle(Message::Preview);
+                } else {
+                    self.dialog = Some(Dialog {
+                        title: String::from("Preset Incomplete"),
+                        body: format!("Couldn't find every install, version and character in preset {:?}. It's loaded as far as it goes, so pick what's missing and copy from there.", preset.name),
+                        actions: vec![],
+                    });
+                }
+            },
+            Message::PresetDelete(name) => {
+                self.presets.retain(|p| p.name != name);
+                if let Err(e) = Preset::save_all(&self.presets) {
+                    self.copy_logs = Some(vec![format!("error saving presets: {}", e)]);
+                }
+            },
+            Message::Theme(theme) => self.theme = theme,
+            Message::Resized(size) => self.window = Some((size.width as u32, size.height as u32)),
             Message::Install => {
-                let inst = wow::prompt_folder();
-                if inst.is_some() {
-                    self.install = inst;
-                    self.src_ver = None;
-                    self.dst_ver = None;
-                    self.src_wtf = None;
-                    self.dst_wtf = None;
+                match prompt_folder() {
+                    None => (),
+                    Some(Err(e)) => self.dialog = Some(error_dialog(&e)),
+                    Some(Ok(install)) => {
+                        if !self.installs.iter().any(|i| i.install_dir == install.install_dir) {
+                            self.installs.push(install.clone());
+                        }
+                        // from the start screen it's used for both sides, otherwise it can be picked in either column
+                        if self.src_install.is_none() {
+                            self.set_install(install);
+                        }
+                    },
                 }
             },
-            Message::Reset(is_source) => {
+            Message::UseInstall(install) => self.set_install(install),
+            Message::PickInstall(install, is_source) => {
                 if is_source {
-                    self.src_ver = None;
-                    self.src_wtf = None;
+                    self.src_install = Some(install);
                 } else {
-                    self.dst_ver = None;
-                    self.dst_wtf = None;
+                    self.dst_install = Some(install);
                 }
+                self.reset(is_source);
             },
+            Message::Reset(is_source) => self.reset(is_source),
             Message::Version(ver, is_source) => {
                 if is_source {
                     self.src_ver = Some(ver)
@@ -128,46 +364,536 @@ impl Operation {
             },
             Message::Wtf(wtf, is_source) => {
                 if is_source {
+                    let install = self.src_install.as_ref().unwrap();
+                    let ver = self.src_ver.as_ref().unwrap();
+                    self.savedvars = match copy::list_saved_variables(install.install_dir.as_ref(), &ver.name, &wtf) {
+                        Ok(s) => s,
+                        Err(e) => {
+                            self.copy_logs = Some(vec![format!("error listing SavedVariables: {}", e)]);
+                            vec![]
+                        }
+                    };
+                    self.src_cvars = vec![];
+                    self.src_macros = vec![];
+                    for scope in [Scope::Account, Scope::Character] {
+                        match copy::read_config_cache(install.install_dir.as_ref(), &ver.name, &wtf, scope) {
+                            Ok(Some(c)) => self.src_cvars.push((scope, c)),
+                            Ok(None) => (),
+                            Err(e) => self.copy_logs = Some(vec![format!("error reading config-cache.wtf: {}", e)]),
+                        }
+                        match copy::read_macros(install.install_dir.as_ref(), &ver.name, &wtf, scope) {
+                            Ok(Some(m)) => self.src_macros.push((scope, m)),
+                            Ok(None) => (),
+                            Err(e) => self.copy_logs = Some(vec![format!("error reading macros-cache.txt: {}", e)]),
+                        }
+                    }
+                    self.src_bindings = match copy::read_bindings(install.install_dir.as_ref(), &ver.name, &wtf) {
+                        Ok(b) => b,
+                        Err(e) => {
+                            self.copy_logs = Some(vec![format!("error reading bindings-cache.wtf: {}", e)]);
+                            None
+                        }
+                    };
                     self.src_wtf = Some(wtf)
                 } else {
-                    self.dst_wtf = Some(wtf)
+                    self.dst_wtfs = vec![wtf];
+                    self.dst_picked = true;
+                }
+            },
+            Message::TargetToggle(wtf, selected) => {
+                self.dst_wtfs.retain(|w| w != &wtf);
+                if selected {
+                    self.dst_wtfs.push(wtf);
+                    self.dst_wtfs.sort();
                 }
             },
-            Message::Copy => {
-                match do_copy(self) {
-                    Ok(l) => self.copy_logs = Some(l),
-                    // todo: show error dialog, rewind directory state
+            Message::TargetAll(wtfs, selected) => {
+                self.dst_wtfs.retain(|w| !wtfs.contains(w));
+                if selected {
+                    self.dst_wtfs.extend(wtfs);
+                    self.dst_wtfs.sort();
+                }
+            },
+            Message::TargetPicked => self.dst_picked = !self.dst_wtfs.is_empty(),
+            Message::Preview => {
+                let reqs = match self.requests() {
+                    Some(r) => r,
+                    None => {
+                        self.copy_logs = Some(vec![String::from("operation not ready for copying!")]);
+                        return
+                    }
+                };
+                match copy::plan_batch(&reqs) {
+                    Ok(p) => self.plan = Some(p),
                     Err(e) => {
-                        self.copy_logs = Some(vec![e.to_string()]);
+                        self.copy_logs = Some(vec![format!("error planning copy: {}", e)]);
+                        self.dialog = Some(error_dialog(&e));
+                    },
+                }
+            },
+            // these can start a task, so update handles them
+            Message::Copy | Message::CopyAnyway | Message::DismissDialog(_) | Message::CloseRequested(_) => (),
+            Message::CopyProgress(progress) => self.progress = Some(progress),
+            Message::CopyDone(result) => {
+                self.progress = None;
+                match result {
+                    Ok(reports) => {
+                        let counts = Counts::of(&reports);
+                        if counts.failed > 0 {
+                            let total = counts.done + counts.failed + counts.skipped;
+                            self.dialog = Some(error_dialog(&Error::PartialCopy { failed: counts.failed, total, not_undone: counts.not_undone }));
+                        }
+                        self.copy_logs = None;
+                        self.report = Some(reports);
+                    },
+                    Err(dialog) => {
+                        self.copy_logs = Some(vec![String::from("the copy couldn't start, nothing was changed")]);
+                        self.dialog = Some(dialog);
+                    },
+                }
+            },
+            Message::ExportReport => {
+                let reports = match &self.report {
+                    Some(r) => r,
+                    None => return,
+                };
+                let file = FileDialog::new()
+                .set_title("Export Copy Report")
+                .set_file_name("copy-report.txt")
+                .save_file();
+                if let Some(file) = file {
+                    if let Err(e) = fs::write(&file, copy::report_text(reports)) {
+                        self.copy_logs = Some(vec![format!("error exporting report to {:?}: {}", file.as_os_str(), e)]);
+                        self.dialog = Some(error_dialog(&e.into()));
+                    }
+                }
+            },
+            Message::StopCopy => self.cancel.store(true, Ordering::Relaxed),
+            Message::Cancel => (),
+            Message::Diff(entry) => {
+                let entry = match entry {
+                    Some(e) => e,
+                    None => {
+                        self.diff = None;
+                        return
                     }
+                };
+                let src = entry.src.as_ref().unwrap_or(&entry.dst);
+                let result = entry.contents().and_then(|contents| {
+                    diff::diff_contents(src, &contents.unwrap_or_default(), &entry.dst)
+                });
+                match result {
+                    Ok(d) => self.diff = Some(d),
+                    Err(e) => self.copy_logs = Some(vec![format!("error comparing {:?}: {}", entry.dst.as_os_str(), e)]),
+                }
+            },
+            Message::SavedVariablesToggle(file, selected) => {
+                if selected {
+                    self.skip_savedvars.remove(&file);
+                } else {
+                    self.skip_savedvars.insert(file);
+                }
+            },
+            Message::SavedVariablesAll(selected) => {
+                for file in &self.savedvars {
+                    if selected {
+                        self.skip_savedvars.remove(file);
+                    } else {
+                        self.skip_savedvars.insert(file.clone());
+                    }
+                }
+            },
+            Message::Cvars(open) => self.cvars_open = open,
+            Message::CvarMode(scope, merge) => {
+                if !merge {
+                    self.merge_cvars.remove(&scope);
+                } else if !self.merge_cvars.contains_key(&scope) {
+                    // start from everything, so switching modes doesn't change what gets copied
+                    let names = self.cvar_names(scope).map(String::from).collect();
+                    self.merge_cvars.insert(scope, names);
+                }
+            },
+            Message::CvarToggle(scope, name, selected) => {
+                if let Some(names) = self.merge_cvars.get_mut(&scope) {
+                    if selected {
+                        names.insert(name);
+                    } else {
+                        names.remove(&name);
+                    }
+                }
+            },
+            Message::CvarCategory(scope, category, selected) => {
+                let in_category: Vec<String> = self.cvar_names(scope)
+                    .filter(|n| Category::of(n) == category)
+                    .map(String::from)
+                    .collect();
+                if let Some(names) = self.merge_cvars.get_mut(&scope) {
+                    for name in in_category {
+                        if selected {
+                            names.insert(name);
+                        } else {
+                            names.remove(&name);
+                        }
+                    }
+                }
+            },
+            Message::KeepInput(input) => self.keep_input = input,
+            Message::KeepAdd => {
+                let name = self.keep_input.trim();
+                if !name.is_empty() {
+                    self.keep_cvars.insert(name.to_owned());
+                }
+                self.keep_input.clear();
+            },
+            Message::KeepRemove(name) => {
+                self.keep_cvars.remove(&name);
+            },
+            Message::KeepDefaults => self.keep_cvars = config::default_keep_cvars(),
+            Message::Bindings(open) => {
+                self.bindings_open = open;
+                self.dst_bindings = None;
+                if !open {
+                    return
+                }
+
+                // targets can each have their own bindings, so only compare against a lone one
+                let install = self.dst_install.as_ref().unwrap();
+                if let (Some(ver), [wtf]) = (&self.dst_ver, self.dst_wtfs.as_slice()) {
+                    match copy::read_bindings(install.install_dir.as_ref(), &ver.name, wtf) {
+                        Ok(b) => self.dst_bindings = b,
+                        Err(e) => self.copy_logs = Some(vec![format!("error reading bindings-cache.wtf: {}", e)]),
+                    }
+                }
+            },
+            Message::BindingsMode(merge) => {
+                self.merge_bindings = match (merge, &self.src_bindings) {
+                    // start from everything, so switching modes doesn't change what gets copied
+                    (true, Some((_, b))) => Some(b.binds().map(|(k, _)| k.to_owned()).collect()),
+                    _ => None,
+                };
+            },
+            Message::BindingToggle(key, selected) => {
+                if let Some(keys) = self.merge_bindings.as_mut() {
+                    if selected {
+                        keys.insert(key);
+                    } else {
+                        keys.remove(&key);
+                    }
+                }
+            },
+            Message::BindingsShown(selected) => {
+                let shown: Vec<String> = self.shown_bindings().map(|(k, _)| k.to_owned()).collect();
+                if let Some(keys) = self.merge_bindings.as_mut() {
+                    for key in shown {
+                        if selected {
+                            keys.insert(key);
+                        } else {
+                            keys.remove(&key);
+                        }
+                    }
+                }
+            },
+            Message::BindingsSearch(search) => self.bindings_search = search,
+            Message::Macros(open) => self.macros_open = open,
+            Message::MacroMode(scope, merge) => {
+                if !merge {
+                    self.merge_macros.remove(&scope);
+                } else if let Some((_, cache)) = self.src_macros.iter().find(|(s, _)| *s == scope) {
+                    // start from everything, so switching modes doesn't change what gets copied
+                    self.merge_macros.insert(scope, cache.macros.iter().map(|m| m.name.clone()).collect());
+                }
+            },
+            Message::MacroToggle(scope, name, selected) => {
+                if let Some(names) = self.merge_macros.get_mut(&scope) {
+                    if selected {
+                        names.insert(name);
+                    } else {
+                        names.remove(&name);
+                    }
+                }
+            },
+            Message::AddonFolders(open) => {
+                self.addon_folders = None;
+                if !open {
+                    return
+                }
+                let (src_version, dst_version) = match (&self.src_install, &self.src_ver, &self.dst_install, &self.dst_ver) {
+                    (Some(si), Some(sv), Some(di), Some(dv)) => (Path::new(&si.install_dir).join(&sv.name), Path::new(&di.install_dir).join(&dv.name)),
+                    _ => return,
+                };
+                match addons::compare_addon_folders(&src_version, &dst_version) {
+                    Ok(f) => self.addon_folders = Some(f),
+                    Err(e) => self.copy_logs = Some(vec![format!("error listing addons: {}", e)]),
+                }
+            },
+            Message::AddonFolderToggle(name, selected) => {
+                if selected {
+                    self.sync_addons.insert(name);
+                } else {
+                    self.sync_addons.remove(&name);
+                }
+            },
+            Message::Snapshots(open) => {
+                if !open {
+                    self.snapshots = None;
+                    return
+                }
+                let mut snapshots: Vec<Snapshot> = vec![];
+                for install in &self.installs {
+                    match backup::list_snapshots(install) {
+                        Ok(s) => snapshots.extend(s),
+                        Err(e) => {
+                            self.copy_logs = Some(vec![format!("error listing snapshots: {}", e)]);
+                            return
+                        }
+                    }
+                }
+                self.snapshots = Some(snapshots);
+            },
+            // restoring is planned and confirmed like a copy, and carried out the same way
+            Message::Restore(snapshot) => {
+                match copy::restore_plan(&snapshot) {
+                    Ok(plan) => {
+                        self.report = None;
+                        self.plan = Some(vec![plan]);
+                    },
+                    Err(e) => {
+                        self.copy_logs = Some(vec![format!("error restoring snapshot: {}", e)]);
+                        self.dialog = Some(error_dialog(&e.into()));
+                    },
+                }
+                self.snapshots = None;
+            },
+            Message::DeleteSnapshot(snapshot) => {
+                if let Err(e) = backup::delete_snapshot(&snapshot) {
+                    self.copy_logs = Some(vec![format!("error deleting snapshot: {}", e)]);
+                }
+                if let Some(snapshots) = self.snapshots.as_mut() {
+                    snapshots.retain(|s| s != &snapshot);
                 }
             },
         }
     }
 
     pub fn theme(&self) -> Theme  {
+        match self.theme {
+            ThemeChoice::Dark => return Theme::SolarizedDark,
+            ThemeChoice::Light => return Theme::SolarizedLight,
+            ThemeChoice::System => (),
+        }
         // there's probably a way to do this without this crate but i'm feeling lazy
-        match dark_light::detect().unwrap_or_else(|_e| dark_light::Mode::Unspecified) {
+        match dark_light::detect().unwrap_or(dark_light::Mode::Unspecified) {
             dark_light::Mode::Dark => Theme::SolarizedDark,
             dark_light::Mode::Light => Theme::SolarizedLight,
             dark_light::Mode::Unspecified => Theme::SolarizedDark,
         }
     }
 
-    fn is_ready(&self) -> bool {
-        self.install.is_some() 
-        && self.src_ver.is_some() 
-        && self.src_wtf.is_some() 
-        && self.dst_ver.is_some()
-        && self.dst_wtf.is_some()
+    // runs the pending plans on another thread so the window keeps responding, reporting back with messages
+    fn start_copy(&mut self, check_clients: bool) -> Task<Message> {
+        // the game would write over the copy when it exits. the plan stays so it can be tried again.
+        if let Some(plans) = self.plan.as_ref().filter(|_| check_clients) {
+            if let Err(e) = client::check_closed(plans) {
+                self.dialog = Some(error_dialog(&e));
+                return Task::none()
+            }
+        }
+        let plans = match self.plan.take() {
+            Some(p) => p,
+            None => return Task::none(),
+        };
+        let cancel = Arc::new(AtomicBool::new(false));
+        self.cancel = cancel.clone();
+        self.report = None;
+        self.progress = Some(Progress { done: 0, total: plans.iter().map(|p| p.entries.len()).sum(), file: PathBuf::new() });
+
+        Task::run(stream::channel(100, move |mut sender| async move {
+            thread::spawn(move || {
+                // sends only fail if the window has been closed, and then there's no one to tell
+                let mut progress = sender.clone();
+                let mut report = |p: Progress| {
+                    let _ = block_on(progress.send(Message::CopyProgress(p)));
+                };
+                let result = match plans.as_slice() {
+                    [plan] => copy::execute_plan_with(plan, &cancel, &mut report)
+                        .map(|r| vec![r])
+                        .map_err(|e| error_dialog(&e)),
+                    _ => Ok(copy::execute_batch_with(&plans, &cancel, &mut report)),
+                };
+                let _ = block_on(sender.send(Message::CopyDone(result)));
+            });
+        }), |message| message)
+    }
+
+    // everything worth remembering for next time
+    fn settings(&self) -> Settings {
+        Settings {
+            installs: self.installs.iter().map(|i| i.install_dir.clone()).collect(),
+            theme: self.theme,
+            window: self.window,
+            selection: self.selection(),
+            options: self.options(),
+        }
+    }
+
+    fn selection(&self) -> Selection {
+        Selection {
+            src_install: self.src_install.as_ref().map(|i| i.install_dir.clone()),
+            src_ver: self.src_ver.as_ref().map(|v| v.name.clone()),
+            src_wtf: self.src_wtf.clone(),
+            dst_install: self.dst_install.as_ref().map(|i| i.install_dir.clone()),
+            dst_ver: self.dst_ver.as_ref().map(|v| v.name.clone()),
+            dst_wtfs: if self.dst_picked {self.dst_wtfs.clone()} else {vec![]},
+        }
+    }
+
+    fn options(&self) -> CopyOptions {
+        CopyOptions {
+            overwrite_account: self.overwrite_account,
+            skip_savedvars: self.skip_savedvars.clone(),
+            keep_cvars: self.keep_cvars.clone(),
+            merge_cvars: self.merge_cvars.clone(),
+            merge_bindings: self.merge_bindings.clone(),
+            merge_macros: self.merge_macros.clone(),
+            sync_addons: self.sync_addons.clone(),
+        }
+    }
+
+    // picks the saved installs, versions and characters again, as far as they still exist.
+    // returns whether everything was found.
+    fn restore(&mut self, sel: &Selection) -> bool {
+        let find = |dir: &Option<OsString>| self.installs.iter().find(|i| Some(&i.install_dir) == dir.as_ref()).cloned();
+        let (src_install, dst_install) = match (find(&sel.src_install), find(&sel.dst_install)) {
+            (Some(s), Some(d)) => (s, d),
+            _ => return false,
+        };
+        self.handle(Message::UseInstall(src_install.clone()));
+        self.handle(Message::PickInstall(dst_install.clone(), false));
+
+        let find_ver = |install: &Install, name: &Option<OsString>| install.versions.iter().find(|v| Some(&v.name) == name.as_ref()).cloned();
+        if let Some(ver) = find_ver(&src_install, &sel.src_ver) {
+            let wtf = ver.wtfs.iter().find(|w| sel.src_wtf.as_ref().is_some_and(|s| settings::same_character(s, w))).cloned();
+            self.handle(Message::Version(ver, true));
+            if let Some(wtf) = wtf {
+                self.handle(Message::Wtf(wtf, true));
+            }
+        }
+        if let Some(ver) = find_ver(&dst_install, &sel.dst_ver) {
+            let wtfs: Vec<Wtf> = ver.wtfs.iter().filter(|w| sel.dst_wtfs.iter().any(|d| settings::same_character(d, w))).cloned().collect();
+            self.handle(Message::Version(ver, false));
+            if !wtfs.is_empty() {
+                self.handle(Message::TargetAll(wtfs, true));
+                self.handle(Message::TargetPicked);
+            }
+        }
+
+        self.src_wtf.is_some() && self.dst_picked && self.dst_wtfs.len() == sel.dst_wtfs.len()
+    }
+
+    // sets everything up the way the preset says, returning whether all of it could be found
+    fn load_preset(&mut self, preset: &Preset) -> bool {
+        // it might use an install that was added on another run and has since been forgotten
+        for dir in [&preset.selection.src_install, &preset.selection.dst_install].into_iter().flatten() {
+            if self.installs.iter().any(|i| &i.install_dir == dir) {
+                continue
+            }
+            if let Ok(install) = wow::get_wow_install(dir.clone()) {
+                self.installs.push(install);
+            }
+        }
+
+        let options = preset.options.clone();
+        self.overwrite_account = options.overwrite_account;
+        self.skip_savedvars = options.skip_savedvars;
+        self.keep_cvars = options.keep_cvars;
+        self.merge_cvars = options.merge_cvars;
+        self.merge_bindings = options.merge_bindings;
+        self.merge_macros = options.merge_macros;
+        self.sync_addons = options.sync_addons;
+        self.presets_open = false;
+
+        let complete = self.restore(&preset.selection);
+        if !complete {
+            self.copy_logs = Some(vec![format!("couldn't find every install, version and character in preset {:?}, it's loaded as far as it goes", preset.name)]);
+        }
+        complete
+    }
+
+    // copies from and to the one install, forgetting everything picked in the old ones
+    fn set_install(&mut self, install: Install) {
+        self.src_install = Some(install.clone());
+        self.dst_install = Some(install);
+        self.snapshots = None;
+        self.reset(true);
+        self.reset(false);
+    }
+
+    // forgets the version and characters picked on one side
+    fn reset(&mut self, is_source: bool) {
+        if is_source {
+            self.src_ver = None;
+            self.src_wtf = None;
+            self.savedvars = vec![];
+            self.src_cvars = vec![];
+            self.src_bindings = None;
+            self.src_macros = vec![];
+        } else {
+            self.dst_ver = None;
+            self.dst_wtfs = vec![];
+            self.dst_picked = false;
+        }
+    }
+
+    // the copies described by the current selection, one per destination, once everything has been picked
+    fn requests(&self) -> Option<Vec<CopyRequest>> {
+        if !self.dst_picked {
+            return None
+        }
+
+        let src_install_dir = &self.src_install.as_ref()?.install_dir;
+        let dst_install_dir = &self.dst_install.as_ref()?.install_dir;
+        let src_ver = &self.src_ver.as_ref()?.name;
+        let src_wtf = self.src_wtf.as_ref()?;
+        let dst_ver = &self.dst_ver.as_ref()?.name;
+
+        Some(self.dst_wtfs.iter().map(|dst_wtf| {
+            let mut req = CopyRequest::new(src_install_dir, src_ver, src_wtf.clone(), dst_ver, dst_wtf.clone());
+            req.dst_install_dir = dst_install_dir.into();
+            req.overwrite_account = self.overwrite_account;
+            req.skip_savedvars = self.skip_savedvars.clone();
+            req.merge_cvars = self.merge_cvars.clone();
+            req.keep_cvars = self.keep_cvars.clone();
+            req.merge_bindings = self.merge_bindings.clone();
+            req.merge_macros = self.merge_macros.clone();
+            req.sync_addons = self.sync_addons.clone();
+            req
+        }).collect())
+    }
+
+    // names of the source's CVars at one scope
+    fn cvar_names(&self, scope: Scope) -> impl Iterator<Item = &str> {
+        self.src_cvars
+            .iter()
+            .filter(move |(s, _)| *s == scope)
+            .flat_map(|(_, c)| c.cvars().map(|(n, _)| n))
+    }
+
+    // the source's bindings that match the search box, by key or action
+    fn shown_bindings(&self) -> impl Iterator<Item = (&str, &str)> {
+        let search = self.bindings_search.to_lowercase();
+        self.src_bindings
+            .iter()
+            .flat_map(|(_, b)| b.binds())
+            .filter(move |(k, a)| k.to_lowercase().contains(&search) || a.to_lowercase().contains(&search))
     }
 
     fn is_same_account(&self) -> Option<bool> {
-        if self.src_wtf.is_none() || self.dst_wtf.is_none() {
+        if self.src_wtf.is_none() || !self.dst_picked {
             return None
         }
 
-        Some(self.src_wtf.as_ref().unwrap().account == self.dst_wtf.as_ref().unwrap().account)
+        let src_account = &self.src_wtf.as_ref().unwrap().account;
+        Some(self.dst_wtfs.iter().all(|w| &w.account == src_account))
     }
 
     fn is_same_ver(&self) -> Option<bool> {
@@ -175,17 +901,58 @@ impl Operation {
             return None
         }
 
-        Some(self.src_ver.as_ref().unwrap() == self.dst_ver.as_ref().unwrap())
+        Some(self.src_install == self.dst_install && self.src_ver.as_ref().unwrap() == self.dst_ver.as_ref().unwrap())
+    }
+
+    pub fn view(&self) -> Element<'_, Message> {
+        let screen = self.screen();
+        match &self.dialog {
+            Some(dialog) => stack![
+                screen,
+                // covers the screen so nothing under the dialog can be clicked
+                opaque(
+                    center(opaque(Operation::dialog_view(dialog)))
+                    .style(|_| container::Style::default().background(Color { a: 0.7, ..Color::BLACK }))
+                )
+            ]
+            .into(),
+            None => screen,
+        }
     }
 
-    pub fn view(&self) -> Element<Message> {
-        if self.install.is_none() {
+    fn screen(&self) -> Element<'_, Message> {
+        if let Some(progress) = &self.progress {
+            return self.progress_view(progress)
+        }
+
+        if self.src_install.is_none() || self.dst_install.is_none() {
+            let mut found = Column::new().spacing(10);
+            if !self.installs.is_empty() {
+                found = found.push(text("Found these WoW installs:"));
+            }
+            for install in &self.installs {
+                found = found.push(
+                    button(text(install.install_dir.to_string_lossy().into_owned()))
+                    .on_press(Message::UseInstall(install.clone()))
+                );
+            }
+            if !self.presets.is_empty() {
+                found = found.push(text("Or run a preset:"));
+            }
+            for preset in &self.presets {
+                found = found.push(
+                    button(text(preset.name.clone()))
+                    .on_press(Message::PresetRun(preset.clone()))
+                    .style(button::success)
+                );
+            }
+
             return container(
-                column![
-                    button(text("Select WoW Install Directory"))
+                found.push(
+                    button(text(if self.installs.is_empty() {"Select WoW Install Directory"} else {"Choose Another Folder"}))
                     .on_press(Message::Install)
-                ]
-                .spacing(10)
+                    .style(if self.installs.is_empty() {button::primary} else {button::secondary})
+                )
             )
             .padding(10)
             .center_x(Fill)
@@ -193,26 +960,100 @@ impl Operation {
             .into()
         }
 
-        let install = self.install.as_ref().unwrap();
+        let src_install = self.src_install.as_ref().unwrap();
+        let dst_install = self.dst_install.as_ref().unwrap();
+
+        if let Some(snapshots) = &self.snapshots {
+            return Operation::snapshot_view(&self.installs, snapshots)
+        }
+
+        if let Some(diff) = &self.diff {
+            return Operation::diff_view(diff)
+        }
 
-        let log = if self.copy_logs.is_none() {
-            scrollable(text(""))
+        if self.presets_open {
+            return self.preset_view()
+        }
+
+        if self.cvars_open {
+            return self.cvar_view()
+        }
+
+        if self.bindings_open {
+            return self.bindings_view()
+        }
+
+        if self.macros_open {
+            return self.macro_view()
+        }
+
+        if let Some(folders) = &self.addon_folders {
+            return self.addon_folder_view(folders)
+        }
+
+        let (log_title, log) = match (&self.plan, &self.copy_logs, &self.report) {
+            (Some(plans), _, _) => ("Planned Changes (click a file to compare it with the target)", scrollable(
+                Operation::plan_view(plans)
+            )),
+            (None, Some(logs), _) => ("Logs", scrollable(
+                text(logs.join("\n")).font(Font::with_name("B612 Mono"))
+            )),
+            (None, None, Some(reports)) => ("Logs", scrollable(
+                Operation::report_view(reports)
+            )),
+            (None, None, None) => ("Logs", scrollable(text(""))),
+        };
+
+        let actions = if self.plan.is_some() {
+            row![
+                button("Confirm")
+                .padding(5)
+                .on_press(Message::Copy)
+                .style(button::success),
+
+                button("Cancel")
+                .padding(5)
+                .on_press(Message::Cancel)
+                .style(button::secondary)
+            ]
         } else {
-            scrollable(
-                text(
-                    self.copy_logs.as_ref().unwrap().join("\n")
-                ).font(Font::with_name("B612 Mono"))
-            )
+            row![
+                button("Go!")
+                .padding(5)
+                .on_press(Message::Preview)
+                .style(button::success)
+            ]
+            .push_maybe((self.copy_logs.is_none() && self.report.is_some()).then(|| {
+                button("Export Report")
+                .padding(5)
+                .on_press(Message::ExportReport)
+                .style(button::secondary)
+            }))
         };
 
         container(
             column![
                 column![
-                    text("Installation Folder: ".to_owned() + install.install_dir.to_str().unwrap())
+                    text(if src_install == dst_install {
+                        format!("Installation Folder: {}", src_install)
+                    } else {
+                        format!("Copying from {} to {}", src_install, dst_install)
+                    })
                     .center(),
 
-                    button("Change")
-                    .on_press(Message::Install)
+                    row![
+                        button("Add Install")
+                        .on_press(Message::Install),
+
+                        button("Restore Snapshot")
+                        .on_press(Message::Snapshots(true)),
+
+                        button("Presets")
+                        .on_press(Message::Presets(true)),
+
+                        pick_list(ThemeChoice::ALL, Some(self.theme), Message::Theme)
+                    ]
+                    .spacing(10)
                 ]
                 .spacing(15),
 
@@ -225,7 +1066,7 @@ impl Operation {
 
                 container(
                     column![
-                        text("Logs").font(Font {
+                        text(log_title).font(Font {
                             weight: font::Weight::Bold,
                             ..Default::default()
                         }),
@@ -246,12 +1087,7 @@ impl Operation {
                 .height(FillPortion(2))
                 .width(Fill),
 
-                row![
-                    button("Go!")
-                    .padding(5)
-                    .on_press(Message::Copy)
-                    .style(button::success)
-                ]
+                actions.spacing(10)
             ]
             .spacing(10)
         )
@@ -262,14 +1098,11 @@ impl Operation {
         .into()
     }
 
-    fn ver_column(&self, is_source: bool) -> Container<Message> {
-        let (ver, wtf) = if is_source {
-            (&self.src_ver, &self.src_wtf)
-        } else {
-            (&self.dst_ver, &self.dst_wtf)
-        };
+    fn ver_column(&self, is_source: bool) -> Container<'_, Message> {
+        let ver = if is_source { &self.src_ver } else { &self.dst_ver };
+        let picked = if is_source { self.src_wtf.is_some() } else { self.dst_picked };
 
-        let install = self.install.as_ref().unwrap();
+        let install = if is_source { self.src_install.as_ref() } else { self.dst_install.as_ref() }.unwrap();
 
         let buttons = if ver.is_none() {
             column(install.versions.iter().map(|v| {
@@ -278,36 +1111,86 @@ impl Operation {
                 .height(50)
                 .into()
             }))
-        } else if wtf.is_none() {
-            column(
-                install.versions
+        } else if !picked {
+            let wtfs = &install.versions
                 .iter()
                 .find(|v| v.to_string() == ver.as_ref().unwrap().to_string())
                 .unwrap()
-                .wtfs
-                .iter()
-                // sometimes, character folders don't have a savedvariables folder.
-                // it doesn't make sense to show these as sources, so don't.
-                .filter(|w| !is_source || (is_source && w.has_vars))
-                .map(|w| {
-                    button(text(w.to_string()).width(Fill).center())
-                    .on_press(Message::Wtf(w.clone(), is_source))
-                    .into()
-                })
-            )
+                .wtfs;
+
+            if is_source {
+                column(
+                    wtfs
+                    .iter()
+                    // sometimes, character folders don't have a savedvariables folder.
+                    // it doesn't make sense to show these as sources, so don't.
+                    .filter(|w| w.has_vars)
+                    .map(|w| {
+                        button(text(w.to_string()).width(Fill).center())
+                        .on_press(Message::Wtf(w.clone(), is_source))
+                        .into()
+                    })
+                )
+            } else {
+                self.target_picker(wtfs)
+            }
+        } else if is_source {
+            let wtf = self.src_wtf.as_ref().unwrap();
+            column![
+                text(format!("Version: {}", ver.as_ref().unwrap())),
+                text(format!("Character: {}", wtf)),
+                text(format!("Account: {}", wtf.account.to_str().unwrap_or_default()))
+            ]
         } else {
-            let toggle = if !is_source && 
-            (!self.is_same_account().unwrap_or(false) || !self.is_same_ver().unwrap_or(false)) {
+            let toggle = if !self.is_same_account().unwrap_or(false) || !self.is_same_ver().unwrap_or(false) {
                 Some(checkbox("Overwrite account-level variables?", self.overwrite_account)
                 .on_toggle(Message::OverwriteToggle))
             } else {
                 None
             };
+            let addons = if self.src_wtf.is_some() {
+                Some(self.addon_list())
+            } else {
+                None
+            };
+            let cvars = if self.src_cvars.is_empty() {
+                None
+            } else {
+                Some(
+                    button("Choose CVars")
+                    .on_press(Message::Cvars(true))
+                    .style(button::secondary)
+                )
+            };
             column![
-                text(format!("Version: {}", ver.as_ref().unwrap().to_string())),
-                text(format!("Character: {}", wtf.as_ref().unwrap().to_string())),
-                text(format!("Account: {}", wtf.as_ref().unwrap().account.to_str().unwrap_or_default()))
-            ].push_maybe(toggle)
+                text(format!("Version: {}", ver.as_ref().unwrap())),
+                text(if self.dst_wtfs.len() == 1 {"Character:"} else {"Characters:"}),
+                column(self.dst_wtfs.iter().map(|w| {
+                    text(format!("{} ({})", w, w.account.to_str().unwrap_or_default())).into()
+                }))
+                .padding([0, 10])
+            ]
+            .push_maybe(toggle)
+            .push_maybe(cvars)
+            .push_maybe(self.src_bindings.as_ref().map(|_| {
+                button("Choose Key Bindings")
+                .on_press(Message::Bindings(true))
+                .style(button::secondary)
+            }))
+            .push_maybe((!self.src_macros.is_empty()).then(|| {
+                button("Choose Macros")
+                .on_press(Message::Macros(true))
+                .style(button::secondary)
+            }))
+            .push_maybe((!self.is_same_ver().unwrap_or(true)).then(|| {
+                button(text(match self.sync_addons.len() {
+                    0 => String::from("Copy Addon Folders"),
+                    n => format!("Copy Addon Folders ({} selected)", n),
+                }))
+                .on_press(Message::AddonFolders(true))
+                .style(button::secondary)
+            }))
+            .push_maybe(addons)
         };
 
         container(
@@ -315,16 +1198,23 @@ impl Operation {
                 text(if is_source {"Source"} else {"Target"}).font(Font {
                     weight: font::Weight::Bold,
                     ..Default::default()
-                }),
-
+                })
+            ]
+            // each side can come from a different install once there's more than one
+            .push_maybe((self.installs.len() > 1).then(|| {
+                pick_list(self.installs.as_slice(), Some(install), move |i| Message::PickInstall(i, is_source))
+                .width(Fill)
+            }))
+            .push(
                 scrollable(
                     buttons.padding(20).spacing(15)
                 )
-                .height(FillPortion(9)),
-
+                .height(FillPortion(9))
+            )
+            .push(
                 button("Reset")
                 .on_press(Message::Reset(is_source))
-            ]
+            )
             .spacing(10)
             .width(Fill)
             .height(Fill)
@@ -342,142 +1232,863 @@ impl Operation {
                 )
                 .background(theme.extended_palette().background.weak.color)
         })
+    }
+
+    // checkboxes for every character in the target version, with shortcuts to pick a whole account or realm
+    fn target_picker<'a>(&'a self, wtfs: &'a [Wtf]) -> Column<'a, Message> {
+        let mut list = Column::new().spacing(5);
+
+        let mut accounts: Vec<&OsString> = wtfs.iter().map(|w| &w.account).collect();
+        accounts.dedup();
+        for account in accounts {
+            let on_account: Vec<Wtf> = wtfs.iter().filter(|w| &w.account == account).cloned().collect();
+            list = list.push(Operation::group_header(account.to_string_lossy().into_owned(), on_account.clone(), 16));
+
+            let mut realms: Vec<&OsString> = on_account.iter().map(|w| &w.realm).collect();
+            realms.dedup();
+            for realm in realms {
+                let on_realm: Vec<Wtf> = on_account.iter().filter(|w| &w.realm == realm).cloned().collect();
+                list = list.push(
+                    container(Operation::group_header(realm.to_string_lossy().into_owned(), on_realm.clone(), 15))
+                    .padding([0, 10])
+                );
+
+                for w in on_realm {
+                    let selected = self.dst_wtfs.contains(&w);
+                    list = list.push(
+                        container(
+                            checkbox(w.character.to_string_lossy().into_owned(), selected)
+                            .on_toggle(move |selected| Message::TargetToggle(w.clone(), selected))
+                        )
+                        .padding([0, 20])
+                    );
+                }
+            }
+        }
+
+        list.push(
+            button("Continue")
+            .on_press_maybe(if self.dst_wtfs.is_empty() { None } else { Some(Message::TargetPicked) })
+        )
+    }
+
+    fn group_header<'a>(name: String, wtfs: Vec<Wtf>, size: u16) -> Element<'a, Message> {
+        row![
+            text(name).size(size).width(Fill),
+
+            button("All")
+            .on_press(Message::TargetAll(wtfs.clone(), true))
+            .style(button::secondary),
+
+            button("None")
+            .on_press(Message::TargetAll(wtfs, false))
+            .style(button::secondary)
+        ]
+        .spacing(10)
+        .align_y(alignment::Vertical::Center)
         .into()
     }
-}
 
-// does the actual copying of config files and savedvariables for a given install, source, and destination
-fn do_copy(op: &Operation) -> Result<Vec<String>, Error> {
-    if !op.is_ready() {
-        return Err(Error::other("operation not ready for copying!"))
-    }
-
-    let mut log: Vec<String> = vec![];
-    let account_files: [&str; 4] = ["bindings-cache.wtf", "config-cache.wtf", "macros-cache.txt", "edit-mode-cache-account.txt"];
-    let character_files: [&str; 5] = ["AddOns.txt", "config-cache.wtf", "layout-local.txt", "macros-cache.txt", "edit-mode-cache-character.txt"];
-
-    let src_account = &op.src_wtf.as_ref().unwrap().account;
-    let dst_account = &op.dst_wtf.as_ref().unwrap().account;
-
-    let src_root = PathBuf::from(&op.install.as_ref().unwrap().install_dir)
-        .join(&op.src_ver.as_ref().unwrap().name)
-        .join("WTF")
-        .join("Account")
-        .join(&src_account);
-
-    let dst_root = PathBuf::from(&op.install.as_ref().unwrap().install_dir)
-        .join(&op.dst_ver.as_ref().unwrap().name)
-        .join("WTF")
-        .join("Account")
-        .join(&dst_account);
-
-    if src_account == dst_account || !op.overwrite_account {
-        log.push(String::from("skipping account copy."));
-    } else {
-        // client configuration
-        for file in account_files {
-            let src = src_root.join(file);
-            let dst = dst_root.join(file);
-            let output = match fs::copy(&src, &dst) {
-                Ok(_) => format!("copied {:?}", src.file_name().unwrap_or_default()),
-                Err(e) => format!("error copying {:?}: {}", src.as_os_str(), e.to_string())
-            };
-            log.push(output);
+    // a checkbox for each addon's SavedVariables in the source, grouped by scope
+    fn addon_list(&self) -> Column<'_, Message> {
+        let mut list = column![
+            row![
+                text("Addons").font(Font {
+                    weight: font::Weight::Bold,
+                    ..Default::default()
+                })
+                .width(Fill),
+
+                button("All")
+                .on_press(Message::SavedVariablesAll(true))
+                .style(button::secondary),
+
+                button("None")
+                .on_press(Message::SavedVariablesAll(false))
+                .style(button::secondary)
+            ]
+            .spacing(10)
+            .align_y(alignment::Vertical::Center)
+        ]
+        .spacing(5);
+
+        for scope in [Scope::Account, Scope::Character] {
+            let mut files = self.savedvars.iter().filter(|f| f.scope == scope).peekable();
+            if files.peek().is_none() {
+                continue
+            }
+
+            list = list.push(text(match scope {
+                Scope::Account => "Account SavedVariables",
+                Scope::Character => "Character SavedVariables",
+            }));
+            for file in files {
+                list = list.push(
+                    checkbox(file.addon(), !self.skip_savedvars.contains(file))
+                    .on_toggle(|selected| Message::SavedVariablesToggle(file.clone(), selected))
+                );
+            }
+        }
+
+        list
+    }
+
+    // the source's CVars by category. each scope either copies its whole config-cache.wtf,
+    // or merges the ticked CVars into the target's and leaves the rest of it alone.
+    fn cvar_view(&self) -> Element<'_, Message> {
+        let bold = Font {
+            weight: font::Weight::Bold,
+            ..Default::default()
+        };
+        let mut list = Column::new().spacing(5);
+
+        list = list.push(text("Never copied, the target keeps its own").font(bold));
+        list = list.push(text("Display and graphics settings that depend on the machine. A * at the end matches every CVar starting with the rest.").size(13));
+        for name in &self.keep_cvars {
+            list = list.push(
+                row![
+                    text(name.clone()).font(Font::with_name("B612 Mono")).width(Fill),
+
+                    button("Remove")
+                    .on_press(Message::KeepRemove(name.clone()))
+                    .style(button::secondary)
+                ]
+                .spacing(10)
+                .align_y(alignment::Vertical::Center)
+            );
         }
+        list = list.push(
+            row![
+                text_input("CVar name", &self.keep_input)
+                .on_input(Message::KeepInput)
+                .on_submit(Message::KeepAdd),
+
+                button("Add")
+                .on_press(Message::KeepAdd),
+
+                button("Defaults")
+                .on_press(Message::KeepDefaults)
+                .style(button::secondary)
+            ]
+            .spacing(10)
+        );
+
+        for (scope, cache) in &self.src_cvars {
+            let scope = *scope;
+            let picked = self.merge_cvars.get(&scope);
+
+            list = list.push(text(match scope {
+                Scope::Account => "Account CVars",
+                Scope::Character => "Character CVars",
+            }).font(bold));
+            list = list.push(
+                checkbox("Only copy the selected CVars, keeping the rest of the target's", picked.is_some())
+                .on_toggle(move |merge| Message::CvarMode(scope, merge))
+            );
+
+            let mut by_category: BTreeMap<Category, Vec<(&str, &str)>> = BTreeMap::new();
+            for (name, value) in cache.cvars() {
+                by_category.entry(Category::of(name)).or_default().push((name, value));
+            }
+
+            for (category, cvars) in by_category {
+                list = list.push(
+                    row![
+                        text(category.to_string()).width(Fill),
 
-        // account saved variables
-        // {install_dir}/WTF/Account/{account number}/SavedVariables
-        let src_savedvars = src_root.join("SavedVariables");
-        let dst_savedvars = dst_root.join("SavedVariables");
+                        button("All")
+                        .on_press_maybe(picked.map(|_| Message::CvarCategory(scope, category, true)))
+                        .style(button::secondary),
 
-        let entries = fs::read_dir(&src_savedvars)?
-        .collect::<Result<Vec<_>, Error>>()?;
+                        button("None")
+                        .on_press_maybe(picked.map(|_| Message::CvarCategory(scope, category, false)))
+                        .style(button::secondary)
+                    ]
+                    .spacing(10)
+                    .align_y(alignment::Vertical::Center)
+                );
 
-        for e in entries {
-            match e.path().extension(){
-                Some(n) => {
-                    if n.to_str().unwrap_or("") != "lua" {
+                for (name, value) in cvars {
+                    if config::is_kept(&self.keep_cvars, name) {
+                        list = list.push(
+                            container(
+                                checkbox(format!("{} = {} (target keeps its own)", name, value), false)
+                                .font(Font::with_name("B612 Mono"))
+                            )
+                            .padding([0, 10])
+                        );
                         continue
                     }
-                    n
-                },
-                None => continue
-            };
-            let src = src_savedvars.join(e.file_name());
-            let dst = dst_savedvars.join(e.file_name());
-            let output = match fs::copy(&src, &dst) {
-                Ok(_) => format!("copied {:?}", src.file_name().unwrap_or_default()),
-                Err(e) => format!("error copying {:?}: {}", src.as_os_str(), e.to_string())
-            };
-            log.push(output);
+
+                    // without a selection the whole file is copied, so everything shows as ticked
+                    let selected = picked.is_none_or(|names| names.contains(name));
+                    let name = name.to_owned();
+                    list = list.push(
+                        container(
+                            checkbox(format!("{} = {}", name, value), selected)
+                            .on_toggle_maybe(picked.map(|_| move |selected| Message::CvarToggle(scope, name.clone(), selected)))
+                            .font(Font::with_name("B612 Mono"))
+                        )
+                        .padding([0, 10])
+                    );
+                }
+            }
         }
 
-        let cache = dst_root.join("cache.md5");
-        let output = match fs::remove_file(&cache) {
-            Ok(_) => format!("removed {:?}", cache.file_name().unwrap_or_default()),
-            Err(e) => format!("error removing {:?}: {}", cache.as_os_str(), e.to_string())
+        container(
+            column![
+                text("CVars").font(bold),
+                horizontal_rule(2),
+                scrollable(list.padding(10)).height(Fill),
+                button("Back")
+                .on_press(Message::Cvars(false))
+            ]
+            .spacing(10)
+        )
+        .padding(10)
+        .width(Fill)
+        .height(Fill)
+        .into()
+    }
+
+    // a searchable table of the source's key bindings, a section for each modifier layer.
+    // keys bound to something else on the target are marked as conflicts.
+    fn bindings_view(&self) -> Element<'_, Message> {
+        let mono = Font::with_name("B612 Mono");
+        let bold = Font {
+            weight: font::Weight::Bold,
+            ..Default::default()
         };
-        log.push(output);
+        let picked = self.merge_bindings.as_ref();
+
+        let mut layers: BTreeMap<String, Vec<(&str, &str)>> = BTreeMap::new();
+        for (key, action) in self.shown_bindings() {
+            layers.entry(bindings::layer(key)).or_default().push((key, action));
+        }
+
+        let mut table = Column::new().spacing(5);
+        for (layer, binds) in layers {
+            table = table.push(text(if layer.is_empty() {String::from("No modifier")} else {layer}).font(bold));
+            for (key, action) in binds {
+                let dst = self.dst_bindings.as_ref().and_then(|(_, b)| b.get(key));
+                let conflict = dst.is_some_and(|d| d != action);
+                let selected = picked.is_none_or(|keys| keys.contains(key));
+                let key = key.to_owned();
+
+                let mut line = row![
+                    checkbox(key.clone(), selected)
+                    .on_toggle_maybe(picked.map(|_| move |selected| Message::BindingToggle(key.clone(), selected)))
+                    .font(mono)
+                    .width(FillPortion(1)),
+
+                    text(action).font(mono).width(FillPortion(2))
+                ]
+                .spacing(10);
+                if self.dst_bindings.is_some() {
+                    line = line.push(
+                        text(format!("{}{}", dst.unwrap_or("-"), if conflict {" (conflict)"} else {""}))
+                        .font(mono)
+                        .width(FillPortion(2))
+                        .style(move |theme: &Theme| text::Style {
+                            color: conflict.then(|| theme.palette().danger),
+                        })
+                    );
+                }
+                table = table.push(container(line).padding([0, 10]));
+            }
+        }
+
+        let mut header = row![
+            text("Key").font(bold).width(FillPortion(1)),
+            text("Source").font(bold).width(FillPortion(2))
+        ]
+        .spacing(10);
+        if self.dst_bindings.is_some() {
+            header = header.push(text("Target").font(bold).width(FillPortion(2)));
+        }
+
+        let whose = |bindings: &Option<(Scope, BindingsCache)>, side: &str| match bindings {
+            Some((Scope::Character, _)) => Some(text(format!("The {} uses character specific key bindings.", side))),
+            _ => None,
+        };
+
+        container(
+            column![
+                text("Key Bindings").font(bold),
+                horizontal_rule(2)
+            ]
+            .push_maybe(whose(&self.src_bindings, "source"))
+            .push_maybe(whose(&self.dst_bindings, "target"))
+            .push(
+                checkbox("Only copy the selected bindings, keeping the rest of the target's", picked.is_some())
+                .on_toggle(Message::BindingsMode)
+            )
+            .push(
+                row![
+                    text_input("Search keys and actions", &self.bindings_search)
+                    .on_input(Message::BindingsSearch),
+
+                    button("All")
+                    .on_press_maybe(picked.map(|_| Message::BindingsShown(true)))
+                    .style(button::secondary),
+
+                    button("None")
+                    .on_press_maybe(picked.map(|_| Message::BindingsShown(false)))
+                    .style(button::secondary)
+                ]
+                .spacing(10)
+                .align_y(alignment::Vertical::Center)
+            )
+            .push(container(header).padding([0, 10]))
+            .push(scrollable(table.padding(10)).height(Fill))
+            .push(
+                button("Back")
+                .on_press(Message::Bindings(false))
+            )
+            .spacing(10)
+        )
+        .padding(10)
+        .width(Fill)
+        .height(Fill)
+        .into()
     }
 
-    // character configuration
-    // {install}/WTF/Account/{account}/{realm}/{character}
+    // the source's macros at each scope. each scope either copies its whole macros-cache.txt, or
+    // merges the ticked macros into the target's by name, as far as the slots go.
+    fn macro_view(&self) -> Element<'_, Message> {
+        let mono = Font::with_name("B612 Mono");
+        let bold = Font {
+            weight: font::Weight::Bold,
+            ..Default::default()
+        };
+        let mut list = Column::new().spacing(5);
+
+        let version = match self.dst_ver.as_ref().or(self.src_ver.as_ref()) {
+            Some(v) => v,
+            None => return list.into(),
+        };
 
-    let src_character = src_root
-    .join(&op.src_wtf.as_ref().unwrap().realm)
-    .join(&op.src_wtf.as_ref().unwrap().character);
+        for (scope, cache) in &self.src_macros {
+            let scope = *scope;
+            let picked = self.merge_macros.get(&scope);
+            let title = match scope {
+                Scope::Account => "General Macros",
+                Scope::Character => "Character Macros",
+            };
+            // the target's slots, since that's where they have to fit
+            let slots = copy::macro_slots(scope, Flavor::of(&version.name));
 
-    let dst_character = dst_root
-    .join(&op.dst_wtf.as_ref().unwrap().realm)
-    .join(&op.dst_wtf.as_ref().unwrap().character);
+            list = list.push(text(format!("{} ({} of {} slots)", title, cache.macros.len(), slots)).font(bold));
+            list = list.push(
+                checkbox("Only copy the selected macros, keeping the rest of the target's", picked.is_some())
+                .on_toggle(move |merge| Message::MacroMode(scope, merge))
+            );
+            if let Some(names) = picked {
+                list = list.push(text(format!("{} selected. Macros the target doesn't have yet only fit if it has free slots.", names.len())).size(13));
+            }
+
+            for m in &cache.macros {
+                // without a selection the whole file is copied, so everything shows as ticked
+                let selected = picked.is_none_or(|names| names.contains(&m.name));
+                let name = m.name.clone();
+                list = list.push(
+                    container(
+                        row![
+                            checkbox(m.name.clone(), selected)
+                            .on_toggle_maybe(picked.map(|_| move |selected| Message::MacroToggle(scope, name.clone(), selected)))
+                            .width(FillPortion(1)),
+
+                            text(m.summary()).font(mono).width(FillPortion(3))
+                        ]
+                        .spacing(10)
+                    )
+                    .padding([0, 10])
+                );
+            }
+        }
+
+        container(
+            column![
+                text("Macros").font(bold),
+                horizontal_rule(2),
+                scrollable(list.padding(10)).height(Fill),
+                button("Back")
+                .on_press(Message::Macros(false))
+            ]
+            .spacing(10)
+        )
+        .padding(10)
+        .width(Fill)
+        .height(Fill)
+        .into()
+    }
 
-    for file in character_files {
-        let src = src_character.join(file);
-        let dst = dst_character.join(file);
-        let output = match fs::copy(&src, &dst) {
-            Ok(_) => format!("copied {:?}", dst.file_name().unwrap_or_default()),
-            Err(e) => format!("error copying {:?}: {}", dst.as_os_str(), e.to_string())
+    // the source version's addon folders, to pick which to copy to the target version.
+    // addons that don't list an interface version for the target's flavor are flagged.
+    fn addon_folder_view<'a>(&'a self, folders: &'a [AddonFolder]) -> Element<'a, Message> {
+        let bold = Font {
+            weight: font::Weight::Bold,
+            ..Default::default()
         };
-        log.push(output);
+        let flavor = Flavor::of(self.dst_ver.as_ref().map(|v| v.name.as_os_str()).unwrap_or_default());
+        let mut list = Column::new().spacing(5);
+
+        for folder in folders {
+            let status = match &folder.status {
+                AddonStatus::Installed => String::from("installed in the target"),
+                AddonStatus::Missing => String::from("not in the target"),
+                AddonStatus::Outdated { src, dst } => format!("older in the target ({}, source has {})", dst, src),
+            };
+            let supported = folder.supported;
+            list = list.push(
+                row![
+                    checkbox(folder.name.clone(), self.sync_addons.contains(&folder.name))
+                    .on_toggle(|selected| Message::AddonFolderToggle(folder.name.clone(), selected))
+                    .width(FillPortion(2)),
+
+                    text(status).width(FillPortion(2)),
+
+                    text(if supported {String::new()} else {format!("doesn't support {}", flavor)})
+                    .width(FillPortion(1))
+                    .style(move |theme: &Theme| text::Style {
+                        color: (!supported).then(|| theme.palette().danger),
+                    })
+                ]
+                .spacing(10)
+            );
+        }
+
+        if folders.is_empty() {
+            list = list.push(text("The source version doesn't have any addons installed."));
+        }
+
+        container(
+            column![
+                text(format!("Addon Folders ({} target)", flavor)).font(bold),
+                horizontal_rule(2),
+                scrollable(list.padding(10)).height(Fill),
+                button("Back")
+                .on_press(Message::AddonFolders(false))
+            ]
+            .spacing(10)
+        )
+        .padding(10)
+        .width(Fill)
+        .height(Fill)
+        .into()
     }
 
-    // character saved variables
-    let src_savedvars = src_character.join("SavedVariables");
-    let dst_savedvars = dst_character.join("SavedVariables");
-    
-    if !dst_savedvars.try_exists()? {
-        log.push(format!("destination savedvariables dir missing, creating: {:?}", dst_savedvars.as_os_str()));
-        fs::create_dir_all(&dst_savedvars)?;
+    // every plan, with a section for each destination if there's more than one.
+    // files that are copied or rewritten can be clicked to see a diff.
+    fn plan_view(plans: &[CopyPlan]) -> Column<'_, Message> {
+        let mono = Font::with_name("B612 Mono");
+        let mut list = Column::new();
+
+        for plan in plans {
+            if plans.len() > 1 {
+                list = list.push(text(plan.header()).font(mono));
+            }
+            for note in &plan.notes {
+                let warning = note.starts_with("warning");
+                list = list.push(
+                    text(note.clone())
+                    .font(mono)
+                    .style(move |theme: &Theme| text::Style {
+                        color: warning.then(|| theme.palette().danger),
+                    })
+                );
+            }
+            for e in &plan.entries {
+                let line = text(plan.entry_line(e)).font(mono);
+                list = list.push(match e.action {
+                    Action::Copy | Action::Overwrite | Action::Rewrite => Element::from(
+                        button(line)
+                        .padding(0)
+                        .style(button::text)
+                        .on_press(Message::Diff(Some(e.clone())))
+                    ),
+                    _ => line.into(),
+                });
+            }
+            if plans.len() > 1 {
+                list = list.push(text(""));
+            }
+        }
+
+        list
     }
 
-    let entries = fs::read_dir(&src_savedvars)?
-    .collect::<Result<Vec<_>, Error>>()?;
+    // the counts, then every entry coloured by how it went
+    fn report_view(reports: &[CopyReport]) -> Column<'_, Message> {
+        let mono = Font::with_name("B612 Mono");
+        let counts = Counts::of(reports);
+        let mut list = Column::new().push(
+            text(counts.to_string())
+            .font(Font {
+                weight: font::Weight::Bold,
+                ..mono
+            })
+            .style(if counts.failed > 0 {text::danger} else {text::success})
+        );
 
-    for e in entries {
-        match e.path().extension(){
-            Some(n) => {
-                if n.to_str().unwrap_or("") != "lua" {
-                    continue
-                }
-                n
-            },
-            None => continue
+        for report in reports {
+            if reports.len() > 1 {
+                list = list.push(text("")).push(text(report.header.clone()).font(mono));
+            }
+            if let Some(snapshot) = &report.snapshot {
+                list = list.push(text(format!("saved snapshot: {:?}", snapshot.file_name().unwrap_or_default())).font(mono));
+            }
+            if let Some(error) = &report.error {
+                list = list.push(text(format!("error: {}", error)).font(mono).style(text::danger));
+            }
+            for note in &report.notes {
+                list = list.push(text(note.clone()).font(mono));
+            }
+            for e in &report.entries {
+                list = list.push(
+                    text(report.entry_line(e))
+                    .font(mono)
+                    .style(match e.status {
+                        Status::Done => text::success,
+                        Status::Failed => text::danger,
+                        Status::Skipped => text::secondary,
+                    })
+                );
+            }
+            if report.cancelled {
+                list = list.push(text("cancelled, nothing was changed").font(mono).style(text::secondary));
+            }
+            if report.rolled_back {
+                list = list.push(text("something failed, so every change was undone and nothing was changed").font(mono).style(text::danger));
+            }
+        }
+
+        list
+    }
+
+    // the source and destination versions of a file next to each other
+    fn diff_view(diff: &FileDiff) -> Element<'_, Message> {
+        // iced slows to a crawl laying out tens of thousands of rows
+        const MAX_ROWS: usize = 2000;
+
+        let mono = Font::with_name("B612 Mono");
+        let bold = Font {
+            weight: font::Weight::Bold,
+            ..Default::default()
         };
-        let src = src_savedvars.join(e.file_name());
-        let dst = dst_savedvars.join(e.file_name());
-        let output = match fs::copy(&src, &dst) {
-            Ok(_) => format!("copied {:?}", dst.file_name().unwrap_or_default()),
-            Err(e) => format!("error copying {:?}: {}", dst.as_os_str(), e.to_string())
+
+        let body: Element<Message> = if diff.is_identical() {
+            text("The files are identical.").into()
+        } else {
+            let mut rows = Column::new();
+            for row in diff.rows.iter().take(MAX_ROWS) {
+                rows = rows.push(diff_row(row, mono));
+            }
+            if diff.rows.len() > MAX_ROWS {
+                rows = rows.push(text(format!("... {} more lines not shown", diff.rows.len() - MAX_ROWS)));
+            }
+            scrollable(rows).height(Fill).into()
         };
-        log.push(output);
+
+        container(
+            column![
+                text(format!("Comparing {}", diff.dst.file_name().unwrap_or_default().to_string_lossy())).font(bold),
+                row![
+                    text(format!("Source: {}", diff.src.to_string_lossy())).width(FillPortion(1)),
+                    text(format!("Target: {}", diff.dst.to_string_lossy())).width(FillPortion(1))
+                ]
+                .spacing(10),
+                horizontal_rule(2),
+                body,
+                button("Back")
+                .on_press(Message::Diff(None))
+            ]
+            .spacing(10)
+        )
+        .padding(10)
+        .width(Fill)
+        .height(Fill)
+        .into()
     }
 
-    let cache = dst_character.join("cache.md5");
-    let output = match fs::remove_file(&cache) {
-        Ok(_) => format!("removed {:?}", cache.file_name().unwrap_or_default()),
-        Err(e) => format!("error removing {:?}: {}", cache.as_os_str(), e.to_string())
+    fn dialog_view(dialog: &Dialog) -> Element<'_, Message> {
+        let mut buttons = row![].spacing(10);
+        for (label, next) in &dialog.actions {
+            buttons = buttons.push(
+                button(*label)
+                .on_press(Message::DismissDialog(Some(Box::new(next.clone()))))
+            );
+        }
+        buttons = buttons.push(
+            button("Close")
+            .on_press(Message::DismissDialog(None))
+            .style(button::secondary)
+        );
+
+        container(
+            column![
+                text(dialog.title.clone()).size(20).font(Font {
+                    weight: font::Weight::Bold,
+                    ..Default::default()
+                }),
+                text(dialog.body.clone()),
+                buttons
+            ]
+            .spacing(15)
+        )
+        .padding(20)
+        .max_width(500)
+        .style(container::bordered_box)
+        .into()
+    }
+
+    // a running copy, with a button to stop it after the file it's on
+    fn progress_view<'a>(&'a self, progress: &'a Progress) -> Element<'a, Message> {
+        let stopping = self.cancel.load(Ordering::Relaxed);
+
+        container(
+            column![
+                text(if progress.file.as_os_str().is_empty() {
+                    String::from("Saving a snapshot...")
+                } else {
+                    format!("Copying {} of {}", progress.done + 1, progress.total)
+                })
+                .font(Font {
+                    weight: font::Weight::Bold,
+                    ..Default::default()
+                }),
+                progress_bar(0.0..=progress.total.max(1) as f32, progress.done as f32),
+                text(progress.file.to_string_lossy()).font(Font::with_name("B612 Mono")).size(13),
+                button(if stopping {"Stopping..."} else {"Cancel"})
+                .on_press_maybe((!stopping).then_some(Message::StopCopy))
+                .style(button::danger)
+            ]
+            .spacing(10)
+            .align_x(alignment::Horizontal::Center)
+        )
+        .padding(20)
+        .center(Fill)
+        .into()
+    }
+
+    // the saved presets, with buttons to run or load them, and a box to save the current selection as one
+    fn preset_view(&self) -> Element<'_, Message> {
+        let bold = Font {
+            weight: font::Weight::Bold,
+            ..Default::default()
+        };
+        let mut list = Column::new().spacing(10);
+
+        for preset in &self.presets {
+            list = list.push(
+                row![
+                    column![
+                        text(preset.name.clone()).font(bold),
+                        text(preset.describe()).size(13)
+                    ]
+                    .width(Fill),
+
+                    button("Run")
+                    .on_press(Message::PresetRun(preset.clone()))
+                    .style(button::success),
+
+                    button("Load")
+                    .on_press(Message::PresetLoad(preset.clone()))
+                    .style(button::secondary),
+
+                    button("Delete")
+                    .on_press(Message::PresetDelete(preset.name.clone()))
+                    .style(button::danger)
+                ]
+                .spacing(10)
+                .align_y(alignment::Vertical::Center)
+            );
+        }
+
+        if self.presets.is_empty() {
+            list = list.push(text("No presets yet. Pick a source and targets, then save them here to run the same copy again later."));
+        }
+
+        let ready = self.requests().is_some() && !self.preset_name.trim().is_empty();
+
+        container(
+            column![
+                text("Presets").font(bold),
+                horizontal_rule(2),
+                scrollable(list.padding(10)).height(Fill),
+                row![
+                    text_input("Preset name", &self.preset_name)
+                    .on_input(Message::PresetName)
+                    .on_submit_maybe(ready.then_some(Message::PresetSave)),
+
+                    button("Save Current Selection")
+                    .on_press_maybe(ready.then_some(Message::PresetSave))
+                ]
+                .spacing(10),
+                button("Back")
+                .on_press(Message::Presets(false))
+            ]
+            .spacing(10)
+        )
+        .padding(10)
+        .width(Fill)
+        .height(Fill)
+        .into()
+    }
+
+    // lists the snapshots taken before each copy, grouped by install and version, with buttons to put them back
+    fn snapshot_view<'a>(installs: &'a [Install], snapshots: &'a [Snapshot]) -> Element<'a, Message> {
+        let mut list = Column::new().spacing(10);
+
+        for (install, ver) in installs.iter().flat_map(|i| i.versions.iter().map(move |v| (i, v))) {
+            let store = Path::new(&install.install_dir).join(&ver.name).join(backup::SNAPSHOT_DIR);
+            let mut ver_snapshots = snapshots.iter().filter(|s| s.dir.starts_with(&store)).peekable();
+            if ver_snapshots.peek().is_none() {
+                continue
+            }
+
+            list = list.push(text(if installs.len() > 1 {format!("{} ({})", ver, install)} else {ver.to_string()}).font(Font {
+                weight: font::Weight::Bold,
+                ..Default::default()
+            }));
+
+            for snapshot in ver_snapshots {
+                list = list.push(
+                    row![
+                        text(snapshot.to_string()).width(Fill),
+
+                        button("Restore")
+                        .on_press(Message::Restore(snapshot.clone()))
+                        .style(button::success),
+
+                        button("Delete")
+                        .on_press(Message::DeleteSnapshot(snapshot.clone()))
+                        .style(button::danger)
+                    ]
+                    .spacing(10)
+                    .align_y(alignment::Vertical::Center)
+                );
+            }
+        }
+
+        if snapshots.is_empty() {
+            list = list.push(text("No snapshots yet. One is taken automatically before every copy."));
+        }
+
+        container(
+            column![
+                text("Snapshots").font(Font {
+                    weight: font::Weight::Bold,
+                    ..Default::default()
+                }),
+                horizontal_rule(2),
+                scrollable(list.padding(10)).height(Fill),
+                button("Back")
+                .on_press(Message::Snapshots(false))
+            ]
+            .spacing(10)
+        )
+        .padding(10)
+        .width(Fill)
+        .height(Fill)
+        .into()
+    }
+}
+
+fn diff_row(row: &DiffRow, mono: Font) -> Element<'_, Message> {
+    if row.kind == RowKind::Skipped {
+        return text("...").font(mono).size(13).into()
+    }
+
+    let cell = |line: &Option<diff::Line>, changed: bool| {
+        let content = match line {
+            Some(l) => format!("{:>5} {}", l.number, l.text),
+            None => String::new(),
+        };
+        let kind = row.kind;
+        container(text(content).font(mono).size(13))
+        .width(FillPortion(1))
+        .style(move |theme: &Theme| {
+            let palette = theme.extended_palette();
+            let color = match kind {
+                _ if !changed => return container::Style::default(),
+                RowKind::Added => palette.success.weak.color,
+                RowKind::Removed => palette.danger.weak.color,
+                _ => palette.primary.weak.color,
+            };
+            container::Style::default().background(color)
+        })
     };
-    log.push(output);
 
-    Ok(log)
-}
\ No newline at end of file
+    row![
+        cell(&row.src, row.kind != RowKind::Equal && row.src.is_some()),
+        cell(&row.dst, row.kind != RowKind::Equal && row.dst.is_some())
+    ]
+    .spacing(10)
+    .into()
+}
+
+// handles prompting the user to pick their wow install directory
+// None if the picker was closed without choosing anything
+fn prompt_folder() -> Option<Result<Install, Error>> {
+    let folder = FileDialog::new()
+    .set_title("Choose WoW Installation Directory")
+    .pick_folder()?
+    .into_os_string();
+
+    Some(wow::get_wow_install(folder))
+}
+
+// what went wrong in plain words, and what to try next
+fn error_dialog(error: &Error) -> Dialog {
+    let choose_another = ("Choose Another Folder", Message::Install);
+    match error {
+        Error::NotAnInstall(dir) => {
+            // a common mistake is picking _retail_ itself rather than the folder it's in
+            let name = dir.file_name().unwrap_or_default().to_string_lossy();
+            let hint = if name.len() > 1 && name.starts_with('_') && name.ends_with('_') {
+                String::from("That looks like one of the game's version folders, pick the folder it's in instead.")
+            } else {
+                String::from("Pick the World of Warcraft folder itself, the one with folders like _retail_ or _classic_ in it.")
+            };
+            Dialog {
+                title: String::from("Not a WoW Install"),
+                body: format!("{} doesn't have any of the game's version folders in it. {}", dir.display(), hint),
+                actions: vec![choose_another],
+            }
+        },
+        Error::NoAccounts(dir) => Dialog {
+            title: String::from("No Characters Yet"),
+            body: format!("The game is in {}, but none of its versions have a WTF/Account folder. The game makes it the first time you log in, so log into a character once and try again, or pick another install.", dir.display()),
+            actions: vec![choose_another],
+        },
+        Error::PermissionDenied(e) => Dialog {
+            title: String::from("Permission Denied"),
+            body: format!("{}. Close WoW and anything else that might have its files open, like a sync or backup program, then try again. If it keeps happening, check that your user can write to the WoW folder.", e),
+            actions: vec![],
+        },
+        Error::PartialCopy { failed, total, not_undone: 0 } => Dialog {
+            title: String::from("Some Changes Failed"),
+            body: format!("{} of {} changes failed. Whenever something fails for a character, everything already changed for it is put back, so it's left as it was. The log says what went wrong. To undo the copies to other characters that did go through, restore their snapshots.", failed, total),
+            actions: vec![("Restore Snapshot", Message::Snapshots(true))],
+        },
+        Error::PartialCopy { failed, total, not_undone } => Dialog {
+            title: String::from("Some Changes Failed"),
+            body: format!("{} of {} changes failed, and {} {} made before the failure couldn't be undone, so a character may be left half copied. The report marks them \"couldn't be undone\". Restore that character's snapshot to put everything back as it was.",
+                failed, total, not_undone, if *not_undone == 1 {"change"} else {"changes"}),
+            actions: vec![("Restore Snapshot", Message::Snapshots(true))],
+        },
+        Error::ClientRunning(clients) => Dialog {
+            title: String::from("WoW Is Running"),
+            body: format!("{} {} running from the version being copied to. The game saves its settings over the WTF folder when it exits, which would undo the copy. Exit the game, then check again, or copy anyway if you're sure it's not the game.",
+                clients.iter().map(|c| c.to_string()).collect::<Vec<_>>().join(", "),
+                if clients.len() == 1 {"is"} else {"are"}),
+            actions: vec![("Check Again", Message::Copy), ("Copy Anyway", Message::CopyAnyway)],
+        },
+        Error::Io(e) => Dialog {
+            title: String::from("Something Went Wrong"),
+            body: format!("{}. The log may have more details.", e),
+            actions: vec![],
+        },
+    }
+}
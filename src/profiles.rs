@@ -0,0 +1,144 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+// Ace3's AceDB remembers which profile each character uses in account-wide SavedVariables:
+//
+//   MyAddonDB = {
+//   	["profileKeys"] = {
+//   		["Main - Area 52"] = "Main - Area 52",
+//   	},
+//   	["profiles"] = { ... },
+//   }
+//
+// a character without an entry gets the default profile, so copying settings to another
+// character means giving it an entry that points at the source character's profile.
+
+use crate::lua::{SavedVariables, Table, Value};
+use crate::wow::Wtf;
+
+// the "Name - Realm" key AceDB uses for a character
+pub fn character_key(wtf: &Wtf) -> String {
+    wtf.to_string()
+}
+
+// cheap check for whether a SavedVariables file could have a profileKeys entry for `key`,
+// so files that don't need rewriting aren't parsed at all
+pub fn might_have_key(contents: &[u8], key: &str) -> bool {
+    contains(contents, b"\"profileKeys\"") && contains(contents, format!("\"{}\"", key).as_bytes())
+}
+
+// points `to` at whatever profile `from` uses, in every profileKeys table in the file.
+// returns how many tables were changed.
+pub fn rewrite_profile_keys(sv: &mut SavedVariables, from: &str, to: &str) -> usize {
+    sv.vars
+        .iter_mut()
+        .filter_map(|(_, v)| v.as_table_mut())
+        .map(|t| rewrite_table(t, from, to))
+        .sum()
+}
+
+fn rewrite_table(table: &mut Table, from: &str, to: &str) -> usize {
+    let mut changed = 0;
+
+    for e in table.entries.iter_mut() {
+        let is_profile_keys = e.key.as_ref().and_then(|k| k.as_str()) == Some("profileKeys");
+        let inner = match e.value.as_table_mut() {
+            Some(t) => t,
+            None => continue,
+        };
+
+        if is_profile_keys {
+            if let Some(profile) = inner.get_str(from).cloned() {
+                if inner.get_str(to) != Some(&profile) {
+                    inner.insert(Value::string(to), profile);
+                    changed += 1;
+                }
+            }
+        } else {
+            changed += rewrite_table(inner, from, to);
+        }
+    }
+
+    changed
+}
+
+fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+    haystack.windows(needle.len()).any(|w| w == needle)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ffi::OsString;
+
+    const DB: &str = "
+MyAddonDB = {
+\t[\"profileKeys\"] = {
+\t\t[\"Main - Area 52\"] = \"Raiding\",
+\t\t[\"Alt - Area 52\"] = \"Alt - Area 52\",
+\t},
+\t[\"profiles\"] = {
+\t\t[\"Raiding\"] = {
+\t\t},
+\t},
+}
+OtherDB = {
+\t[\"global\"] = {
+\t\t[\"profileKeys\"] = {
+\t\t\t[\"Main - Area 52\"] = \"Default\",
+\t\t},
+\t},
+}
+";
+
+    fn wtf(realm: &str, character: &str) -> Wtf {
+        Wtf {
+            account: OsString::from("ACC"),
+            realm: OsString::from(realm),
+            character: OsString::from(character),
+            has_vars: false,
+        }
+    }
+
+    #[test]
+    fn keys_with_odd_realms() {
+        assert_eq!(character_key(&wtf("Area 52", "Main")), "Main - Area 52");
+        assert_eq!(character_key(&wtf("Azjol-Nerub", "Alt")), "Alt - Azjol-Nerub");
+
+        let file = b"DB = {\n\t[\"profileKeys\"] = {\n\t\t[\"Alt - Azjol-Nerub\"] = \"Default\",\n\t},\n}\n";
+        assert!(might_have_key(file, "Alt - Azjol-Nerub"));
+        assert!(!might_have_key(file, "Alt - Area 52"));
+        assert!(!might_have_key(b"DB = {\n\t[\"Alt - Azjol-Nerub\"] = true,\n}\n", "Alt - Azjol-Nerub"));
+    }
+
+    #[test]
+    fn existing_key_is_overwritten() {
+        let mut sv = SavedVariables::parse(DB.as_bytes()).unwrap();
+        assert_eq!(rewrite_profile_keys(&mut sv, "Main - Area 52", "Alt - Area 52"), 2);
+        let out = String::from_utf8(sv.to_bytes()).unwrap();
+        // the alt's entry points at the main's profile instead of being added a second time
+        assert!(out.contains("\t\t[\"Alt - Area 52\"] = \"Raiding\",\n\t},"));
+        assert_eq!(out.matches("[\"Alt - Area 52\"]").count(), 2);
+        assert!(out.contains("\t\t\t[\"Alt - Area 52\"] = \"Default\",\n"));
+
+        // already pointing there, so nothing changes
+        assert_eq!(rewrite_profile_keys(&mut sv, "Main - Area 52", "Alt - Area 52"), 0);
+    }
+
+    #[test]
+    fn files_without_profile_keys_are_left_alone() {
+        let file = "\nWeakAurasSaved = {\n\t[\"displays\"] = {\n\t\t[\"Main - Area 52\"] = {\n\t\t},\n\t},\n}\n";
+        assert!(!might_have_key(file.as_bytes(), "Main - Area 52"));
+        let mut sv = SavedVariables::parse(file.as_bytes()).unwrap();
+        assert_eq!(rewrite_profile_keys(&mut sv, "Main - Area 52", "Alt - Area 52"), 0);
+        assert_eq!(sv.to_bytes(), file.as_bytes());
+
+        // or with profileKeys but not for the source character
+        let mut sv = SavedVariables::parse(DB.as_bytes()).unwrap();
+        assert_eq!(rewrite_profile_keys(&mut sv, "Nobody - Area 52", "Alt - Area 52"), 0);
+        assert_eq!(sv.to_bytes(), DB.as_bytes());
+    }
+}
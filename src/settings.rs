@@ -0,0 +1,508 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+// what the app remembers between runs, kept in the platform's config folder.
+// settings.txt has one entry per line:
+//
+//   install <folder>                          every install that was found or added
+//   theme system|dark|light
+//   window <width> <height>
+//
+// followed by the last selection and copy options:
+//
+//   source-install <folder>
+//   source-version <version folder name>
+//   source-character <account>/<realm>/<character>
+//   target-install <folder>
+//   target-version <version folder name>
+//   target-character <account>/<realm>/<character>   one line per target
+//   overwrite-account true|false
+//   skip account|character <SavedVariables file name>
+//   keep-cvars <names>                        left out to use the defaults
+//   account-cvars|character-cvars <names>     left out to copy the whole file
+//   bindings <keys>                           the same
+//   account-macros|character-macros <names>   the same
+//   sync-addons <names>
+//
+// where <names> and <keys> are comma separated, like on the command line, with a comma or backslash
+// that's part of a name written as \, or \\ (the , key's binding, or a macro called "Heal, Target").
+// presets.txt holds named copies of those last entries, each starting with `preset <name>`.
+// unknown lines are skipped, so an older build can read a newer file.
+
+use crate::config;
+use crate::copy::{CopyRequest, SavedVariablesFile, Scope};
+use crate::wow::{self, Install, Wtf};
+use std::{collections::{BTreeMap, BTreeSet}, env, ffi::OsString, fmt, fs, io::{Error, ErrorKind}, path::{Path, PathBuf}};
+
+const SETTINGS_FILE: &str = "settings.txt";
+const PRESETS_FILE: &str = "presets.txt";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ThemeChoice {
+    // follow the system's dark or light mode
+    #[default]
+    System,
+    Dark,
+    Light,
+}
+
+// the installs, versions and characters picked on each side, by name
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Selection {
+    pub src_install: Option<OsString>,
+    pub src_ver: Option<OsString>,
+    pub src_wtf: Option<Wtf>,
+    pub dst_install: Option<OsString>,
+    pub dst_ver: Option<OsString>,
+    pub dst_wtfs: Vec<Wtf>,
+}
+
+// everything about a copy besides who it's from and to, see CopyRequest
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CopyOptions {
+    pub overwrite_account: bool,
+    pub skip_savedvars: BTreeSet<SavedVariablesFile>,
+    pub keep_cvars: BTreeSet<String>,
+    pub merge_cvars: BTreeMap<Scope, BTreeSet<String>>,
+    pub merge_bindings: Option<BTreeSet<String>>,
+    pub merge_macros: BTreeMap<Scope, BTreeSet<String>>,
+    pub sync_addons: BTreeSet<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Settings {
+    pub installs: Vec<OsString>,
+    pub theme: ThemeChoice,
+    pub window: Option<(u32, u32)>,
+    pub selection: Selection,
+    pub options: CopyOptions,
+}
+
+// a saved copy that can be run again, e.g. main on retail to main on the PTR every patch
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Preset {
+    pub name: String,
+    pub selection: Selection,
+    pub options: CopyOptions,
+}
+
+impl ThemeChoice {
+    pub const ALL: [ThemeChoice; 3] = [ThemeChoice::System, ThemeChoice::Dark, ThemeChoice::Light];
+}
+
+impl fmt::Display for ThemeChoice {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ThemeChoice::System => "System",
+            ThemeChoice::Dark => "Dark",
+            ThemeChoice::Light => "Light",
+        })
+    }
+}
+
+impl Default for CopyOptions {
+    fn default() -> Self {
+        CopyOptions {
+            overwrite_account: true,
+            skip_savedvars: BTreeSet::new(),
+            keep_cvars: config::default_keep_cvars(),
+            merge_cvars: BTreeMap::new(),
+            merge_bindings: None,
+            merge_macros: BTreeMap::new(),
+            sync_addons: BTreeSet::new(),
+        }
+    }
+}
+
+impl Selection {
+    // takes one line of a settings file, if it's about the selection
+    fn read(&mut self, key: &str, value: &str) -> bool {
+        match key {
+            "source-install" => self.src_install = Some(OsString::from(value)),
+            "source-version" => self.src_ver = Some(OsString::from(value)),
+            "source-character" => self.src_wtf = parse_character(value),
+            "target-install" => self.dst_install = Some(OsString::from(value)),
+            "target-version" => self.dst_ver = Some(OsString::from(value)),
+            "target-character" => self.dst_wtfs.extend(parse_character(value)),
+            _ => return false,
+        }
+        true
+    }
+}
+
+impl fmt::Display for Selection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let lines = [
+            ("source-install", self.src_install.as_ref().map(|i| i.to_string_lossy().into_owned())),
+            ("source-version", self.src_ver.as_ref().map(|v| v.to_string_lossy().into_owned())),
+            ("source-character", self.src_wtf.as_ref().map(character)),
+            ("target-install", self.dst_install.as_ref().map(|i| i.to_string_lossy().into_owned())),
+            ("target-version", self.dst_ver.as_ref().map(|v| v.to_string_lossy().into_owned())),
+        ];
+        for (key, value) in lines {
+            if let Some(value) = value {
+                writeln!(f, "{} {}", key, value)?;
+            }
+        }
+        for wtf in &self.dst_wtfs {
+            writeln!(f, "target-character {}", character(wtf))?;
+        }
+        Ok(())
+    }
+}
+
+impl CopyOptions {
+    // takes one line of a settings file, if it's about the copy options
+    fn read(&mut self, key: &str, value: &str) -> bool {
+        match key {
+            "overwrite-account" => self.overwrite_account = value != "false",
+            "skip" => if let Some((scope, file_name)) = value.split_once(' ') {
+                if let Some(scope) = parse_scope(scope) {
+                    self.skip_savedvars.insert(SavedVariablesFile { scope, file_name: OsString::from(file_name) });
+                }
+            },
+            "keep-cvars" => self.keep_cvars = parse_list(value),
+            "account-cvars" | "character-cvars" => {
+                let scope = if key == "account-cvars" {Scope::Account} else {Scope::Character};
+                self.merge_cvars.insert(scope, parse_list(value));
+            },
+            "bindings" => self.merge_bindings = Some(parse_list(value)),
+            "account-macros" | "character-macros" => {
+                let scope = if key == "account-macros" {Scope::Account} else {Scope::Character};
+                self.merge_macros.insert(scope, parse_list(value));
+            },
+            "sync-addons" => self.sync_addons = parse_list(value),
+            _ => return false,
+        }
+        true
+    }
+}
+
+impl fmt::Display for CopyOptions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "overwrite-account {}", self.overwrite_account)?;
+        for file in &self.skip_savedvars {
+            writeln!(f, "skip {} {}", scope_name(file.scope), file.file_name.to_string_lossy())?;
+        }
+        if self.keep_cvars != config::default_keep_cvars() {
+            writeln!(f, "keep-cvars {}", join(&self.keep_cvars))?;
+        }
+        for (scope, names) in &self.merge_cvars {
+            writeln!(f, "{}-cvars {}", scope_name(*scope), join(names))?;
+        }
+        if let Some(keys) = &self.merge_bindings {
+            writeln!(f, "bindings {}", join(keys))?;
+        }
+        for (scope, names) in &self.merge_macros {
+            writeln!(f, "{}-macros {}", scope_name(*scope), join(names))?;
+        }
+        if !self.sync_addons.is_empty() {
+            writeln!(f, "sync-addons {}", join(&self.sync_addons))?;
+        }
+        Ok(())
+    }
+}
+
+impl Settings {
+    pub fn parse(input: &str) -> Settings {
+        let mut settings = Settings::default();
+
+        for (key, value) in lines(input) {
+            if settings.selection.read(key, value) || settings.options.read(key, value) {
+                continue
+            }
+            match key {
+                "install" => settings.installs.push(OsString::from(value)),
+                "theme" => settings.theme = match value {
+                    "dark" => ThemeChoice::Dark,
+                    "light" => ThemeChoice::Light,
+                    _ => ThemeChoice::System,
+                },
+                "window" => settings.window = value
+                    .split_once(' ')
+                    .and_then(|(w, h)| Some((w.parse().ok()?, h.parse().ok()?))),
+                _ => continue,
+            }
+        }
+
+        settings
+    }
+
+    // the saved settings, or the defaults if nothing has been saved yet
+    pub fn load() -> Result<Settings, Error> {
+        Ok(read_file(SETTINGS_FILE)?.map(|s| Settings::parse(&s)).unwrap_or_default())
+    }
+
+    pub fn save(&self) -> Result<(), Error> {
+        write_file(SETTINGS_FILE, &self.to_string())
+    }
+}
+
+impl fmt::Display for Settings {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for install in &self.installs {
+            writeln!(f, "install {}", install.to_string_lossy())?;
+        }
+        writeln!(f, "theme {}", self.theme.to_string().to_lowercase())?;
+        if let Some((width, height)) = self.window {
+            writeln!(f, "window {} {}", width, height)?;
+        }
+        write!(f, "{}{}", self.selection, self.options)
+    }
+}
+
+impl Preset {
+    pub fn parse_all(input: &str) -> Vec<Preset> {
+        let mut presets: Vec<Preset> = vec![];
+
+        for (key, value) in lines(input) {
+            if key == "preset" {
+                presets.push(Preset {
+                    name: value.to_owned(),
+                    selection: Selection::default(),
+                    options: CopyOptions::default(),
+                });
+            } else if let Some(preset) = presets.last_mut() {
+                if !preset.selection.read(key, value) {
+                    preset.options.read(key, value);
+                }
+            }
+        }
+
+        presets
+    }
+
+    pub fn load_all() -> Result<Vec<Preset>, Error> {
+        Ok(read_file(PRESETS_FILE)?.map(|s| Preset::parse_all(&s)).unwrap_or_default())
+    }
+
+    pub fn save_all(presets: &[Preset]) -> Result<(), Error> {
+        write_file(PRESETS_FILE, &presets.iter().map(Preset::to_string).collect::<Vec<_>>().join("\n"))
+    }
+
+    // e.g. "_retail_/1234#1/Area 52/Main to _ptr_/1234#1/Area 52/Main"
+    pub fn describe(&self) -> String {
+        let sel = &self.selection;
+        let side = |ver: &Option<OsString>, wtf: &Wtf| format!("{}/{}",
+            ver.as_deref().unwrap_or_default().to_string_lossy(),
+            character(wtf)
+        );
+        let targets: Vec<String> = sel.dst_wtfs.iter().map(|w| side(&sel.dst_ver, w)).collect();
+        let mut out = format!("{} to {}",
+            sel.src_wtf.as_ref().map(|w| side(&sel.src_ver, w)).unwrap_or_default(),
+            targets.join(", ")
+        );
+        if sel.dst_install != sel.src_install {
+            out += &format!(" in {}", sel.dst_install.as_deref().unwrap_or_default().to_string_lossy());
+        }
+        out
+    }
+
+    // the copies this preset describes, against the installs as they are now.
+    // fails if any of its installs, versions or characters have gone away.
+    pub fn requests(&self) -> Result<Vec<CopyRequest>, Error> {
+        let sel = &self.selection;
+        let (src_dir, src_ver, src_wtf, dst_dir, dst_ver) = match (&sel.src_install, &sel.src_ver, &sel.src_wtf, &sel.dst_install, &sel.dst_ver) {
+            (Some(si), Some(sv), Some(sw), Some(di), Some(dv)) if !sel.dst_wtfs.is_empty() => (si, sv, sw, di, dv),
+            _ => return Err(Error::other(format!("preset {:?} doesn't have both a source and a target", self.name))),
+        };
+
+        let src_install = wow::get_wow_install(src_dir.clone())?;
+        let dst_install = if dst_dir == src_dir {src_install.clone()} else {wow::get_wow_install(dst_dir.clone())?};
+        let src_wtf = find_character(&src_install, src_ver, src_wtf)?;
+
+        let mut reqs: Vec<CopyRequest> = vec![];
+        for dst_wtf in &sel.dst_wtfs {
+            let mut req = CopyRequest::new(src_dir, src_ver, src_wtf.clone(), dst_ver, find_character(&dst_install, dst_ver, dst_wtf)?);
+            req.dst_install_dir = PathBuf::from(dst_dir);
+            req.overwrite_account = self.options.overwrite_account;
+            req.skip_savedvars.clone_from(&self.options.skip_savedvars);
+            req.keep_cvars.clone_from(&self.options.keep_cvars);
+            req.merge_cvars.clone_from(&self.options.merge_cvars);
+            req.merge_bindings.clone_from(&self.options.merge_bindings);
+            req.merge_macros.clone_from(&self.options.merge_macros);
+            req.sync_addons.clone_from(&self.options.sync_addons);
+            reqs.push(req);
+        }
+        Ok(reqs)
+    }
+}
+
+impl fmt::Display for Preset {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "preset {}", self.name)?;
+        write!(f, "{}{}", self.selection, self.options)
+    }
+}
+
+// where settings are kept on this platform
+pub fn settings_dir() -> Option<PathBuf> {
+    let base = if cfg!(target_os = "windows") {
+        PathBuf::from(env::var_os("APPDATA")?)
+    } else if cfg!(target_os = "macos") {
+        PathBuf::from(env::var_os("HOME")?).join("Library").join("Application Support")
+    } else {
+        match env::var_os("XDG_CONFIG_HOME").map(PathBuf::from) {
+            Some(dir) if dir.is_absolute() => dir,
+            _ => PathBuf::from(env::var_os("HOME")?).join(".config"),
+        }
+    };
+    Some(base.join("wow-profile-copy-ng"))
+}
+
+// the same characters as a Wtf scanned from disk, which also knows whether it has SavedVariables
+pub fn same_character(a: &Wtf, b: &Wtf) -> bool {
+    a.account == b.account && a.realm == b.realm && a.character == b.character
+}
+
+fn find_character(install: &Install, ver: &OsString, wtf: &Wtf) -> Result<Wtf, Error> {
+    install.versions
+        .iter()
+        .find(|v| &v.name == ver)
+        .and_then(|v| v.wtfs.iter().find(|w| same_character(w, wtf)))
+        .cloned()
+        .ok_or_else(|| Error::new(ErrorKind::NotFound, format!("no character {} in {}/{}",
+            character(wtf),
+            install.install_dir.to_string_lossy(),
+            ver.to_string_lossy()
+        )))
+}
+
+// `key value` pairs, skipping anything else
+fn lines(input: &str) -> impl Iterator<Item = (&str, &str)> {
+    input.lines().filter_map(|line| line.split_once(' '))
+}
+
+fn read_file(name: &str) -> Result<Option<String>, Error> {
+    let path = match settings_dir() {
+        Some(dir) => dir.join(name),
+        None => return Ok(None),
+    };
+    match fs::read(path) {
+        Ok(contents) => Ok(Some(String::from_utf8_lossy(&contents).into_owned())),
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+fn write_file(name: &str, contents: &str) -> Result<(), Error> {
+    let dir = settings_dir().ok_or_else(|| Error::other("couldn't find a folder to keep settings in"))?;
+    fs::create_dir_all(&dir)?;
+    replace_file(&dir.join(name), contents)
+}
+
+// written to a temporary file first and renamed over the old one, so a crash partway leaves the old one whole
+fn replace_file(path: &Path, contents: &str) -> Result<(), Error> {
+    let mut tmp = OsString::from(".");
+    tmp.push(path.file_name().unwrap_or_default());
+    tmp.push(".new");
+    let tmp = path.with_file_name(tmp);
+    fs::write(&tmp, contents)?;
+    fs::rename(&tmp, path)
+}
+
+fn character(wtf: &Wtf) -> String {
+    format!("{}/{}/{}",
+        wtf.account.to_string_lossy(),
+        wtf.realm.to_string_lossy(),
+        wtf.character.to_string_lossy()
+    )
+}
+
+// folder names can't have a / in them, so it's safe to split on
+fn parse_character(value: &str) -> Option<Wtf> {
+    let mut parts = value.splitn(3, '/');
+    Some(Wtf {
+        account: OsString::from(parts.next()?),
+        realm: OsString::from(parts.next()?),
+        character: OsString::from(parts.next()?),
+        has_vars: false,
+    })
+}
+
+fn scope_name(scope: Scope) -> &'static str {
+    match scope {
+        Scope::Account => "account",
+        Scope::Character => "character",
+    }
+}
+
+fn parse_scope(name: &str) -> Option<Scope> {
+    match name {
+        "account" => Some(Scope::Account),
+        "character" => Some(Scope::Character),
+        _ => None,
+    }
+}
+
+// a comma separated list, as written by join. also used for the command line's lists.
+pub fn parse_list(value: &str) -> BTreeSet<String> {
+    let mut names: Vec<String> = vec![String::new()];
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => names.last_mut().unwrap().extend(chars.next()),
+            ',' => names.push(String::new()),
+            c => names.last_mut().unwrap().push(c),
+        }
+    }
+    names.iter().map(|n| n.trim()).filter(|n| !n.is_empty()).map(String::from).collect()
+}
+
+fn join(names: &BTreeSet<String>) -> String {
+    names
+        .iter()
+        .map(|n| n.replace('\\', "\\\\").replace(',', "\\,"))
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lists_round_trip() {
+        let names = |list: &[&str]| list.iter().map(|n| n.to_string()).collect::<BTreeSet<String>>();
+        let mut settings = Settings::default();
+        settings.options.merge_bindings = Some(names(&[",", "SHIFT-,", "\\", "CTRL-F"]));
+        settings.options.merge_macros.insert(Scope::Account, names(&["Heal, Target", "Pyroblast"]));
+        settings.options.merge_cvars.insert(Scope::Character, BTreeSet::new());
+        settings.options.sync_addons = names(&["WeakAuras", "Details"]);
+
+        let written = settings.to_string();
+        assert!(written.contains("bindings \\,,CTRL-F,SHIFT-\\,,\\\\\n"), "{}", written);
+        assert!(written.contains("account-macros Heal\\, Target,Pyroblast\n"), "{}", written);
+        assert_eq!(Settings::parse(&written), settings);
+
+        let preset = Preset { name: String::from("main to ptr"), selection: Selection::default(), options: settings.options };
+        assert_eq!(Preset::parse_all(&preset.to_string()), vec![preset]);
+    }
+
+    #[test]
+    fn lists_read_before_escaping() {
+        // files written before commas were escaped read the same as they always did
+        assert_eq!(parse_list("SHIFT-1, CTRL-F,,"), BTreeSet::from([String::from("SHIFT-1"), String::from("CTRL-F")]));
+    }
+
+    #[test]
+    fn files_are_replaced_whole() {
+        let dir = env::temp_dir().join(format!("wow-profile-copy-ng-settings-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(SETTINGS_FILE);
+        fs::write(&path, "theme dark\nwindow 1024 768\n").unwrap();
+
+        replace_file(&path, "theme light\n").unwrap();
+        let written = fs::read_to_string(&path).unwrap();
+        let files = fs::read_dir(&dir).unwrap().count();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(written, "theme light\n");
+        // the temporary file was renamed into place
+        assert_eq!(files, 1);
+    }
+}
@@ -4,10 +4,10 @@
  * file, You can obtain one at https://mozilla.org/MPL/2.0/.
  */
 
-use std::{ffi::OsString, io::{self, Error, ErrorKind}, fs::{self, DirEntry}};
-use rfd::FileDialog;
+use crate::error::Error;
+use std::{env, ffi::OsString, io::{self, ErrorKind}, fs::{self, DirEntry}, path::{Path, PathBuf}};
 
-#[derive(Default, Debug, Clone)]
+#[derive(Default, Debug, Clone, PartialEq, Eq)]
 pub struct Install {
     pub install_dir: OsString,
     pub versions: Vec<Version>
@@ -27,6 +27,12 @@ pub struct Wtf {
     pub has_vars: bool
 }
 
+impl std::fmt::Display for Install {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.install_dir.to_string_lossy())
+    }
+}
+
 impl std::fmt::Display for Version {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let ver = self.name.to_str().unwrap_or_default();
@@ -50,9 +56,97 @@ impl std::fmt::Display for Wtf {
     }
 }
 
+// where the launcher puts the game by default on this platform
+pub fn default_install_dir() -> Option<OsString> {
+    if cfg!(target_os = "windows") {
+        Some(OsString::from("C:\\Program Files (x86)\\World of Warcraft"))
+    } else if cfg!(target_os = "macos") {
+        Some(OsString::from("/Applications/World of Warcraft"))
+    } else if cfg!(target_os = "linux") {
+        let home = env::var_os("HOME")?;
+        Some(PathBuf::from(home)
+            .join("Games/battlenet/drive_c/Program Files (x86)/World of Warcraft")
+            .into_os_string())
+    } else {
+        None
+    }
+}
+
+// every install that can be found without asking, the default location first.
+// on Linux the game runs under Wine, so this looks through the prefixes the usual launchers make.
+pub fn find_installs() -> Vec<Install> {
+    let mut dirs: Vec<PathBuf> = default_install_dir().into_iter().map(PathBuf::from).collect();
+    if cfg!(target_os = "linux") {
+        if let Some(home) = env::var_os("HOME") {
+            dirs.extend(wine_install_dirs(&PathBuf::from(home)));
+        }
+    }
+
+    let mut installs: Vec<Install> = vec![];
+    let mut seen: Vec<PathBuf> = vec![];
+    for dir in dirs {
+        // the same prefix can be reachable through a symlink, e.g. ~/.steam/steam
+        let real = match fs::canonicalize(&dir) {
+            Ok(real) => real,
+            Err(_) => continue,
+        };
+        if seen.contains(&real) {
+            continue
+        }
+        if let Ok(install) = get_wow_install(dir.into_os_string()) {
+            seen.push(real);
+            installs.push(install);
+        }
+    }
+    installs
+}
+
+// "World of Warcraft" folders in the Wine prefixes under a home folder: ~/.wine, Lutris games in
+// ~/Games, Bottles, Steam's Proton prefixes, Heroic, and the Flatpak versions of each.
+// none of these are checked for a WoW install yet.
+fn wine_install_dirs(home: &Path) -> Vec<PathBuf> {
+    let mut prefixes: Vec<PathBuf> = vec![home.join(".wine")];
+
+    // folders that hold one prefix per subfolder
+    let prefix_parents = [
+        "Games",
+        "Games/Heroic/Prefixes",
+        ".local/share/bottles/bottles",
+        ".var/app/com.usebottles.bottles/data/bottles/bottles",
+        ".var/app/net.lutris.Lutris/data/lutris/prefixes",
+        ".var/app/com.heroicgameslauncher.hgl/config/heroic/Prefixes",
+    ];
+    // Proton keeps each game's prefix in a pfx folder, under the game's Steam id
+    let compatdata = [
+        ".local/share/Steam/steamapps/compatdata",
+        ".steam/steam/steamapps/compatdata",
+        ".var/app/com.valvesoftware.Steam/data/Steam/steamapps/compatdata",
+        ".var/app/com.valvesoftware.Steam/.local/share/Steam/steamapps/compatdata",
+    ];
+
+    let subfolders = |parent: &str| -> Vec<PathBuf> {
+        match fs::read_dir(home.join(parent)) {
+            Ok(entries) => entries.flatten().map(|e| e.path()).collect(),
+            Err(_) => vec![],
+        }
+    };
+    for parent in prefix_parents {
+        prefixes.extend(subfolders(parent));
+    }
+    for parent in compatdata {
+        prefixes.extend(subfolders(parent).into_iter().map(|p| p.join("pfx")));
+    }
+
+    prefixes
+        .iter()
+        .flat_map(|prefix| ["Program Files (x86)", "Program Files"].map(|p| prefix.join("drive_c").join(p).join("World of Warcraft")))
+        .filter(|dir| dir.is_dir())
+        .collect()
+}
+
 // tries reading a directory and finding information about a WoW install
-// errors if the directory doesn't appear to contain a WoW install
-pub fn get_wow_install(dir: OsString) -> Result<Install, io::Error> {
+// errors if the directory doesn't appear to contain a WoW install, or none of its versions have characters
+pub fn get_wow_install(dir: OsString) -> Result<Install, Error> {
     let mut found_install: bool = false;
     let entries = fs::read_dir(&dir)?
         .collect::<Result<Vec<_>, io::Error>>()?;
@@ -60,7 +154,8 @@ pub fn get_wow_install(dir: OsString) -> Result<Install, io::Error> {
     let mut versions: Vec<Version> = Vec::new();
 
     for e in entries {
-        let file_name = e.file_name().into_string().unwrap();
+        // folders found while looking through Wine prefixes can have any name, not just UTF-8 ones
+        let file_name = e.file_name().to_string_lossy().into_owned();
         // version folders have names like _classic_
         if e.file_type()?.is_dir() && file_name.starts_with("_") && file_name.ends_with("_") {
             found_install = true;
@@ -73,18 +168,21 @@ pub fn get_wow_install(dir: OsString) -> Result<Install, io::Error> {
             };
             versions.push(Version {
                 name: e.file_name(),
-                wtfs: wtfs
+                wtfs
             })
         }
     }
 
     if !found_install {
-        return Err(Error::other("didn't find a wow install here"))
+        return Err(Error::NotAnInstall(PathBuf::from(dir)))
+    }
+    if versions.is_empty() {
+        return Err(Error::NoAccounts(PathBuf::from(dir)))
     }
 
     Ok(Install {
         install_dir: dir,
-        versions: versions
+        versions
     })
 }
 
@@ -98,12 +196,12 @@ fn get_wtf_configurations(version: &DirEntry) -> Result<Vec<Wtf>, io::Error> {
 
     for account in acc_entries {
         if account.file_type()?.is_dir() && account.file_name() != "SavedVariables" { // assume that any dir that isn't SavedVariables here is a realm
-            let realm_entries = fs::read_dir(&account.path())?
+            let realm_entries = fs::read_dir(account.path())?
             .collect::<Result<Vec<_>, io::Error>>()?;
             for realm in realm_entries {
                 if realm.file_type()?.is_dir() && realm.file_name() != "SavedVariables" {
                     // any subdirectories of the realm directory are characters, they have arbitrary names
-                    let char_entries = fs::read_dir(&realm.path())?
+                    let char_entries = fs::read_dir(realm.path())?
                     .collect::<Result<Vec<_>, io::Error>>()?;
                     for char in char_entries {
                         if char.file_type()?.is_dir() {
@@ -125,18 +223,52 @@ fn get_wtf_configurations(version: &DirEntry) -> Result<Vec<Wtf>, io::Error> {
     Ok(wtfs)
 }
 
-// handles prompting the user to pick their wow install directory
-pub fn prompt_folder() -> Option<Install> {
-    let folder = FileDialog::new()
-    .set_title("Choose WoW Installation Directory")
-    .pick_folder()?
-    .into_os_string();
-
-    match get_wow_install(folder) {
-        Ok(install) => Some(install),
-        Err(_) => {
-            // todo: display error information dialog
-            prompt_folder()
-        },
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wine_prefixes() {
+        let home = env::temp_dir().join(format!("wow-profile-copy-ng-wow-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&home);
+        let install = |prefix: &str, program_files: &str| {
+            let dir = home.join(prefix).join("drive_c").join(program_files).join("World of Warcraft");
+            fs::create_dir_all(&dir).unwrap();
+            dir
+        };
+        let found = [
+            install(".wine", "Program Files (x86)"),
+            install("Games/battlenet", "Program Files (x86)"),
+            install("Games/Heroic/Prefixes/wow", "Program Files"),
+            install(".local/share/Steam/steamapps/compatdata/123/pfx", "Program Files (x86)"),
+        ];
+        // not a prefix, and a prefix without the game
+        fs::create_dir_all(home.join("Games/Other/World of Warcraft")).unwrap();
+        fs::create_dir_all(home.join(".local/share/bottles/bottles/empty/drive_c/Program Files (x86)")).unwrap();
+
+        let mut dirs = wine_install_dirs(&home);
+        fs::remove_dir_all(&home).unwrap();
+
+        dirs.sort();
+        let mut expected = found.to_vec();
+        expected.sort();
+        assert_eq!(dirs, expected);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn odd_folder_names() {
+        use std::{ffi::OsStr, os::unix::ffi::OsStrExt};
+
+        let dir = env::temp_dir().join(format!("wow-profile-copy-ng-wow-names-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("_retail_").join("WTF/Account/ACC/Area 52/Main")).unwrap();
+        fs::create_dir_all(dir.join(OsStr::from_bytes(b"\xff_broken_"))).unwrap();
+
+        let install = get_wow_install(dir.clone().into_os_string());
+        fs::remove_dir_all(&dir).unwrap();
+
+        let names: Vec<OsString> = install.unwrap().versions.into_iter().map(|v| v.name).collect();
+        assert_eq!(names, [OsString::from("_retail_")]);
     }
 }